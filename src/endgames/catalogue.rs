@@ -0,0 +1,223 @@
+use owlchess::{Board, Color, Coord, File, Piece, Rank};
+
+use super::{
+    generator::{distance, light_squares, GenerationRules, MaterialSpec},
+    goal::Goal,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedResult {
+    Win,
+    Draw,
+}
+
+/// A named kind of endgame to practise, along with the rules used to generate it.
+pub struct EndgameType {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub material: &'static str,
+    /// The side with the extra material.
+    pub winning_side: Color,
+    /// Result with best play from both sides.
+    pub expected_result: ExpectedResult,
+    /// What the player has to achieve.
+    pub goal: Goal,
+    rules: fn(GenerationRules) -> GenerationRules,
+    side_to_move: Color,
+}
+
+impl EndgameType {
+    /// The side the user practises: the attacker in won endgames, the defender in drawn ones.
+    pub fn player_side(&self) -> Color {
+        match self.expected_result {
+            ExpectedResult::Win => self.winning_side,
+            ExpectedResult::Draw => self.winning_side.inv(),
+        }
+    }
+
+    pub fn generation_rules(&self) -> GenerationRules {
+        let material: MaterialSpec = self
+            .material
+            .parse()
+            .expect("catalogue material must be valid");
+        (self.rules)(GenerationRules::new(&material, self.side_to_move))
+    }
+}
+
+pub const CATALOGUE: &[EndgameType] = &[
+    EndgameType {
+        id: "KQK",
+        name: "King and queen against king",
+        material: "KQ vs K",
+        winning_side: Color::White,
+        expected_result: ExpectedResult::Win,
+        goal: Goal::Mate,
+        rules: |rules| rules,
+        side_to_move: Color::White,
+    },
+    EndgameType {
+        id: "KRK",
+        name: "King and rook against king",
+        material: "KR vs K",
+        winning_side: Color::White,
+        expected_result: ExpectedResult::Win,
+        goal: Goal::Mate,
+        rules: |rules| rules,
+        side_to_move: Color::White,
+    },
+    EndgameType {
+        id: "KBBK",
+        name: "King and two bishops against king",
+        material: "KBB vs K",
+        winning_side: Color::White,
+        expected_result: ExpectedResult::Win,
+        goal: Goal::Mate,
+        rules: |rules| rules.check(bishops_on_both_colors),
+        side_to_move: Color::White,
+    },
+    EndgameType {
+        id: "KBNK",
+        name: "King, bishop and knight against king",
+        material: "KBN vs K",
+        winning_side: Color::White,
+        expected_result: ExpectedResult::Win,
+        goal: Goal::Mate,
+        rules: |rules| rules,
+        side_to_move: Color::White,
+    },
+    EndgameType {
+        id: "KPK",
+        name: "King and pawn against king",
+        material: "KP vs K",
+        winning_side: Color::White,
+        expected_result: ExpectedResult::Win,
+        goal: Goal::PromoteSafely,
+        rules: |rules| {
+            rules
+                .constrain(Color::White, Piece::Pawn, |pawn| {
+                    pawn.files(&[File::B, File::C, File::D, File::E, File::F, File::G])
+                        .ranks(&[Rank::R2, Rank::R3, Rank::R4])
+                })
+                .check(king_on_pawn_key_square)
+        },
+        side_to_move: Color::White,
+    },
+    EndgameType {
+        id: "KQKR",
+        name: "King and queen against king and rook",
+        material: "KQ vs KR",
+        winning_side: Color::White,
+        expected_result: ExpectedResult::Win,
+        goal: Goal::WinMaterial,
+        rules: |rules| rules.check(queen_safe_from_rook),
+        side_to_move: Color::White,
+    },
+    EndgameType {
+        id: "KRPKR-Lucena",
+        name: "Rook and pawn against rook: Lucena position",
+        material: "KRP vs KR",
+        winning_side: Color::White,
+        expected_result: ExpectedResult::Win,
+        goal: Goal::PromoteSafely,
+        rules: |rules| {
+            rules
+                .constrain(Color::White, Piece::Pawn, |pawn| {
+                    pawn.files(&[File::C, File::D, File::E, File::F])
+                        .ranks(&[Rank::R7])
+                })
+                .constrain(Color::White, Piece::King, |king| king.ranks(&[Rank::R8]))
+                .constrain(Color::Black, Piece::Rook, |rook| {
+                    rook.ranks(&[Rank::R1, Rank::R2, Rank::R3])
+                })
+                .check(lucena_setup)
+        },
+        side_to_move: Color::White,
+    },
+    EndgameType {
+        id: "KRPKR-Philidor",
+        name: "Rook and pawn against rook: Philidor defence",
+        material: "KRP vs KR",
+        winning_side: Color::White,
+        expected_result: ExpectedResult::Draw,
+        goal: Goal::Draw,
+        rules: |rules| {
+            rules
+                .constrain(Color::White, Piece::Pawn, |pawn| {
+                    pawn.files(&[File::B, File::C, File::D, File::E, File::F, File::G])
+                        .ranks(&[Rank::R5])
+                })
+                .constrain(Color::White, Piece::King, |king| {
+                    king.ranks(&[Rank::R4, Rank::R5])
+                })
+                .constrain(Color::White, Piece::Rook, |rook| {
+                    rook.ranks(&[Rank::R1, Rank::R2, Rank::R3, Rank::R4, Rank::R5])
+                })
+                .constrain(Color::Black, Piece::King, |king| king.ranks(&[Rank::R8]))
+                .constrain(Color::Black, Piece::Rook, |rook| rook.ranks(&[Rank::R6]))
+                .check(philidor_setup)
+        },
+        side_to_move: Color::Black,
+    },
+];
+
+fn single(board: &Board, color: Color, piece: Piece) -> Coord {
+    board
+        .piece2(color, piece)
+        .into_iter()
+        .next()
+        .expect("piece must be on the board")
+}
+
+fn bishops_on_both_colors(board: &Board) -> bool {
+    let bishops = board.piece2(Color::White, Piece::Bishop);
+    (bishops & light_squares()).len() == 1
+}
+
+/// With the king two ranks ahead of its pawn (rook pawns excepted), White always wins.
+fn king_on_pawn_key_square(board: &Board) -> bool {
+    let pawn = single(board, Color::White, Piece::Pawn);
+    let white_king = board.king_pos(Color::White);
+    let black_king = board.king_pos(Color::Black);
+    let key_squares = [-1, 0, 1]
+        .iter()
+        .filter_map(|&delta_file| pawn.shift(delta_file, -2))
+        .collect::<Vec<_>>();
+    key_squares.contains(&white_king) && distance(black_king, pawn) > 1
+}
+
+fn queen_safe_from_rook(board: &Board) -> bool {
+    let queen = single(board, Color::White, Piece::Queen);
+    let rook = single(board, Color::Black, Piece::Rook);
+    let white_king = board.king_pos(Color::White);
+    let aligned = |coord: Coord| coord.file() == rook.file() || coord.rank() == rook.rank();
+    !board.is_check() && !aligned(queen) && !aligned(white_king)
+}
+
+fn lucena_setup(board: &Board) -> bool {
+    let pawn = single(board, Color::White, Piece::Pawn);
+    let white_king = board.king_pos(Color::White);
+    let white_rook = single(board, Color::White, Piece::Rook);
+    let black_king = board.king_pos(Color::Black);
+
+    let king_in_front = white_king.file() == pawn.file();
+    let cut_off_files = black_king.file().index().abs_diff(pawn.file().index()) >= 2;
+    let rook_between = {
+        let (low, high) = if black_king.file() < pawn.file() {
+            (black_king.file(), pawn.file())
+        } else {
+            (pawn.file(), black_king.file())
+        };
+        white_rook.file() > low && white_rook.file() < high
+    };
+    king_in_front && cut_off_files && rook_between && !board.is_check()
+}
+
+fn philidor_setup(board: &Board) -> bool {
+    let pawn = single(board, Color::White, Piece::Pawn);
+    let white_king = board.king_pos(Color::White);
+    let black_king = board.king_pos(Color::Black);
+
+    let defender_in_front = black_king.file().index().abs_diff(pawn.file().index()) <= 1;
+    let attacker_near_pawn = distance(white_king, pawn) <= 1;
+    defender_in_front && attacker_near_pawn && !board.is_check()
+}
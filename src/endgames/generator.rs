@@ -0,0 +1,328 @@
+use std::{fmt, str::FromStr};
+
+use owlchess::{Bitboard, Board, CastlingRights, Cell, Color, Coord, File, Piece, Rank, RawBoard};
+use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+
+/// How many random placements are tried before giving up on a set of rules.
+pub const MAX_ATTEMPTS: usize = 100_000;
+
+/// Pieces of both sides, kings included.
+///
+/// Can be parsed from strings such as "KQ vs K", "KRP vs KR" or "KBN v K".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialSpec {
+    pub white: Vec<Piece>,
+    pub black: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaterialParseError {
+    MissingSeparator,
+    UnknownPiece(char),
+    MissingKing(Color),
+    TooManyKings(Color),
+}
+
+impl fmt::Display for MaterialParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSeparator => write!(f, "expected the two sides separated by \"vs\""),
+            Self::UnknownPiece(c) => write!(f, "unknown piece letter '{}'", c),
+            Self::MissingKing(color) => write!(f, "{} has no king", color.as_long_str()),
+            Self::TooManyKings(color) => write!(f, "{} has several kings", color.as_long_str()),
+        }
+    }
+}
+
+impl std::error::Error for MaterialParseError {}
+
+impl FromStr for MaterialSpec {
+    type Err = MaterialParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.replace("vs", "v");
+        let (white, black) = normalized
+            .split_once('v')
+            .ok_or(MaterialParseError::MissingSeparator)?;
+        Ok(Self {
+            white: parse_side(white, Color::White)?,
+            black: parse_side(black, Color::Black)?,
+        })
+    }
+}
+
+fn parse_side(side: &str, color: Color) -> Result<Vec<Piece>, MaterialParseError> {
+    let pieces = side
+        .trim()
+        .chars()
+        .map(|c| match c.to_ascii_uppercase() {
+            'K' => Ok(Piece::King),
+            'Q' => Ok(Piece::Queen),
+            'R' => Ok(Piece::Rook),
+            'B' => Ok(Piece::Bishop),
+            'N' => Ok(Piece::Knight),
+            'P' => Ok(Piece::Pawn),
+            _ => Err(MaterialParseError::UnknownPiece(c)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match pieces.iter().filter(|&&p| p == Piece::King).count() {
+        0 => Err(MaterialParseError::MissingKing(color)),
+        1 => Ok(pieces),
+        _ => Err(MaterialParseError::TooManyKings(color)),
+    }
+}
+
+/// Allowed squares for a single piece of the generated position.
+#[derive(Debug, Clone, Copy)]
+pub struct PieceConstraint {
+    pub color: Color,
+    pub piece: Piece,
+    pub allowed: Bitboard,
+}
+
+impl PieceConstraint {
+    pub fn new(color: Color, piece: Piece) -> Self {
+        let allowed = if piece == Piece::Pawn {
+            !(rank_mask(Rank::R1) | rank_mask(Rank::R8))
+        } else {
+            Bitboard::FULL
+        };
+        Self {
+            color,
+            piece,
+            allowed,
+        }
+    }
+
+    pub fn files(mut self, files: &[File]) -> Self {
+        let mask = files
+            .iter()
+            .fold(Bitboard::EMPTY, |acc, &file| acc | file_mask(file));
+        self.allowed &= mask;
+        self
+    }
+
+    pub fn ranks(mut self, ranks: &[Rank]) -> Self {
+        let mask = ranks
+            .iter()
+            .fold(Bitboard::EMPTY, |acc, &rank| acc | rank_mask(rank));
+        self.allowed &= mask;
+        self
+    }
+}
+
+/// Everything needed to generate a position: the pieces with their allowed squares,
+/// the side to move and optional extra checks on the whole position.
+///
+/// Some rules are always enforced: pawns are never put on the 1st/8th rank, kings are
+/// never adjacent, the side not to move is never in check, and the side to move always
+/// has at least one legal move.
+#[derive(Debug, Clone)]
+pub struct GenerationRules {
+    pub pieces: Vec<PieceConstraint>,
+    pub side_to_move: Color,
+    pub extra_checks: Vec<fn(&Board) -> bool>,
+}
+
+impl GenerationRules {
+    pub fn new(material: &MaterialSpec, side_to_move: Color) -> Self {
+        let white = material
+            .white
+            .iter()
+            .map(|&piece| PieceConstraint::new(Color::White, piece));
+        let black = material
+            .black
+            .iter()
+            .map(|&piece| PieceConstraint::new(Color::Black, piece));
+        Self {
+            pieces: white.chain(black).collect(),
+            side_to_move,
+            extra_checks: vec![],
+        }
+    }
+
+    /// Restricts every piece of the given kind and color with `constrain`.
+    pub fn constrain(
+        mut self,
+        color: Color,
+        piece: Piece,
+        constrain: impl Fn(PieceConstraint) -> PieceConstraint,
+    ) -> Self {
+        for constraint in self
+            .pieces
+            .iter_mut()
+            .filter(|c| c.color == color && c.piece == piece)
+        {
+            *constraint = constrain(*constraint);
+        }
+        self
+    }
+
+    pub fn check(mut self, check: fn(&Board) -> bool) -> Self {
+        self.extra_checks.push(check);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationError {
+    NoLegalPosition,
+}
+
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoLegalPosition => {
+                write!(f, "no legal position found after {} attempts", MAX_ATTEMPTS)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GenerationError {}
+
+/// Random position generator: the same seed and rules always give the same position.
+pub struct PositionGenerator {
+    rng: StdRng,
+}
+
+impl PositionGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn generate(&mut self, rules: &GenerationRules) -> Result<Board, GenerationError> {
+        for _ in 0..MAX_ATTEMPTS {
+            if let Some(board) = self.try_generate(rules) {
+                return Ok(board);
+            }
+        }
+        Err(GenerationError::NoLegalPosition)
+    }
+
+    fn try_generate(&mut self, rules: &GenerationRules) -> Option<Board> {
+        let mut raw = RawBoard::empty();
+        raw.castling = CastlingRights::EMPTY;
+        raw.side = rules.side_to_move;
+
+        let mut occupied = Bitboard::EMPTY;
+        for constraint in &rules.pieces {
+            let free = constraint.allowed & !occupied;
+            let coord = free.into_iter().choose(&mut self.rng)?;
+            raw.put(coord, Cell::from_parts(constraint.color, constraint.piece));
+            occupied.set(coord);
+        }
+
+        // Also rejects a side not to move which is in check.
+        let board = Board::try_from(raw).ok()?;
+
+        if kings_adjacent(&board) || !board.has_legal_moves() {
+            return None;
+        }
+        if !rules.extra_checks.iter().all(|check| check(&board)) {
+            return None;
+        }
+        Some(board)
+    }
+}
+
+fn kings_adjacent(board: &Board) -> bool {
+    let white_king = board.king_pos(Color::White);
+    let black_king = board.king_pos(Color::Black);
+    distance(white_king, black_king) <= 1
+}
+
+/// Chebyshev distance between two squares, i.e. the number of king moves between them.
+pub fn distance(first: Coord, second: Coord) -> usize {
+    let files = first.file().index().abs_diff(second.file().index());
+    let ranks = first.rank().index().abs_diff(second.rank().index());
+    files.max(ranks)
+}
+
+pub fn file_mask(file: File) -> Bitboard {
+    Rank::iter().fold(Bitboard::EMPTY, |acc, rank| acc.with2(file, rank))
+}
+
+pub fn rank_mask(rank: Rank) -> Bitboard {
+    File::iter().fold(Bitboard::EMPTY, |acc, file| acc.with2(file, rank))
+}
+
+/// Squares of the same color as h1.
+pub fn light_squares() -> Bitboard {
+    Coord::iter()
+        .filter(|coord| (coord.file().index() + coord.rank().index()) % 2 == 0)
+        .fold(Bitboard::EMPTY, |acc, coord| acc.with(coord))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kpk_rules() -> GenerationRules {
+        GenerationRules::new(&"KP vs K".parse().unwrap(), Color::White).constrain(
+            Color::White,
+            Piece::Pawn,
+            |pawn| pawn.files(&[File::E]).ranks(&[Rank::R2, Rank::R3]),
+        )
+    }
+
+    #[test]
+    fn same_seed_gives_same_position() {
+        let rules = kpk_rules();
+        for seed in [0, 1, 42, u64::MAX] {
+            let first = PositionGenerator::new(seed).generate(&rules).unwrap();
+            let second = PositionGenerator::new(seed).generate(&rules).unwrap();
+            assert_eq!(first.as_fen(), second.as_fen());
+        }
+    }
+
+    #[test]
+    fn constraints_are_respected() {
+        let rules = kpk_rules().check(|board| board.king_pos(Color::Black).rank() == Rank::R8);
+        let mut generator = PositionGenerator::new(7);
+        for _ in 0..50 {
+            let board = generator.generate(&rules).unwrap();
+            let pawn = board
+                .piece2(Color::White, Piece::Pawn)
+                .into_iter()
+                .next()
+                .unwrap();
+            assert_eq!(pawn.file(), File::E);
+            assert!([Rank::R2, Rank::R3].contains(&pawn.rank()));
+            assert_eq!(board.king_pos(Color::Black).rank(), Rank::R8);
+            assert!(!kings_adjacent(&board));
+            assert_eq!(board.side(), Color::White);
+        }
+    }
+
+    #[test]
+    fn impossible_rules_are_reported() {
+        let rules = kpk_rules().check(|_| false);
+        assert_eq!(
+            PositionGenerator::new(0).generate(&rules),
+            Err(GenerationError::NoLegalPosition)
+        );
+    }
+
+    #[test]
+    fn material_parsing() {
+        let spec: MaterialSpec = "KBN v K".parse().unwrap();
+        assert_eq!(spec.white, [Piece::King, Piece::Bishop, Piece::Knight]);
+        assert_eq!(spec.black, [Piece::King]);
+        assert_eq!(
+            "KQ K".parse::<MaterialSpec>(),
+            Err(MaterialParseError::MissingSeparator)
+        );
+        assert_eq!(
+            "KX vs K".parse::<MaterialSpec>(),
+            Err(MaterialParseError::UnknownPiece('X'))
+        );
+        assert_eq!(
+            "Q vs K".parse::<MaterialSpec>(),
+            Err(MaterialParseError::MissingKing(Color::White))
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+
+use owlchess::{Board, Color, DrawReason, Outcome, Piece};
+
+/// What the player has to achieve in a drill, which may differ from the plain game result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    Mate,
+    /// Mate in at most this many moves of the player.
+    MateWithin(u32),
+    /// End up, on the player's turn, with a better material balance than at the start.
+    WinMaterial,
+    /// Reach a draw, whatever the way.
+    Draw,
+    /// Promote a pawn and keep the new piece until the player's next turn.
+    PromoteSafely,
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mate => write!(f, "mate"),
+            Self::MateWithin(moves) => write!(f, "mate within {} moves", moves),
+            Self::WinMaterial => write!(f, "win material"),
+            Self::Draw => write!(f, "reach a draw"),
+            Self::PromoteSafely => write!(f, "promote safely"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Checkmated,
+    Stalemated,
+    InsufficientMaterial,
+    Repetition,
+    ExceededFiftyMoves,
+    TooSlow(u32),
+    MaterialLost,
+    Abandoned,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Success,
+    Failed(Failure),
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failure = match self {
+            Self::Success => return write!(f, "success"),
+            Self::Failed(failure) => failure,
+        };
+        match failure {
+            Failure::Checkmated => write!(f, "failed: checkmated"),
+            Failure::Stalemated => write!(f, "failed: stalemated"),
+            Failure::InsufficientMaterial => write!(f, "failed: not enough material left to mate"),
+            Failure::Repetition => write!(f, "failed: position repeated"),
+            Failure::ExceededFiftyMoves => write!(f, "failed: exceeded 50 moves"),
+            Failure::TooSlow(moves) => write!(f, "failed: no mate within {} moves", moves),
+            Failure::MaterialLost => write!(f, "failed: material lost"),
+            Failure::Abandoned => write!(f, "failed: abandoned"),
+        }
+    }
+}
+
+/// Evaluates a drill against its goal, from its starting position.
+pub struct Judge {
+    goal: Goal,
+    player: Color,
+    start: Board,
+}
+
+impl Judge {
+    pub fn new(goal: Goal, player: Color, start: &Board) -> Self {
+        Self {
+            goal,
+            player,
+            start: start.clone(),
+        }
+    }
+
+    pub fn goal(&self) -> Goal {
+        self.goal
+    }
+
+    /// Judges the current position of the drill, and its outcome if the game is over.
+    ///
+    /// Returns `None` as long as the drill is not decided.
+    pub fn judge(&self, position: &Board, outcome: Option<Outcome>) -> Option<Verdict> {
+        let player_turn = position.side() == self.player;
+        match self.goal {
+            Goal::Mate => outcome.map(|outcome| self.judge_win(outcome)),
+            Goal::MateWithin(moves) => match outcome {
+                Some(Outcome::Win { side, .. }) if side == self.player => Some(Verdict::Success),
+                Some(outcome) => Some(self.judge_win(outcome)),
+                None if self.player_moves(position) >= moves => {
+                    Some(Verdict::Failed(Failure::TooSlow(moves)))
+                }
+                None => None,
+            },
+            Goal::WinMaterial => {
+                let won_material =
+                    balance(position, self.player) > balance(&self.start, self.player);
+                match outcome {
+                    Some(outcome) => Some(self.judge_win(outcome)),
+                    None if player_turn && won_material => Some(Verdict::Success),
+                    None => None,
+                }
+            }
+            Goal::Draw => match outcome {
+                Some(Outcome::Draw(_)) => Some(Verdict::Success),
+                Some(Outcome::Win { side, .. }) if side == self.player => Some(Verdict::Success),
+                Some(Outcome::Win { .. }) => Some(Verdict::Failed(Failure::Checkmated)),
+                None => None,
+            },
+            Goal::PromoteSafely => {
+                if let Some(outcome) = outcome {
+                    return Some(self.judge_win(outcome));
+                }
+                let pawns = |board: &Board| board.piece2(self.player, Piece::Pawn).len();
+                let pieces = |board: &Board| {
+                    (board.color(self.player) & !board.piece2(self.player, Piece::Pawn)).len()
+                };
+                let promoted = pieces(position) > pieces(&self.start);
+                let pawn_lost = pawns(position) < pawns(&self.start) && !promoted;
+                if pawn_lost || pieces(position) < pieces(&self.start) {
+                    Some(Verdict::Failed(Failure::MaterialLost))
+                } else if promoted && player_turn {
+                    Some(Verdict::Success)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Verdict given when the player leaves a drill before it is decided.
+    pub fn abandon(&self) -> Verdict {
+        Verdict::Failed(Failure::Abandoned)
+    }
+
+    /// Verdict for a finished game, when the player is expected to win it.
+    fn judge_win(&self, outcome: Outcome) -> Verdict {
+        match outcome {
+            Outcome::Win { side, .. } if side == self.player => Verdict::Success,
+            Outcome::Win { .. } => Verdict::Failed(Failure::Checkmated),
+            Outcome::Draw(reason) => Verdict::Failed(match reason {
+                DrawReason::Stalemate => Failure::Stalemated,
+                DrawReason::InsufficientMaterial => Failure::InsufficientMaterial,
+                DrawReason::Repeat3 | DrawReason::Repeat5 => Failure::Repetition,
+                DrawReason::Moves50 | DrawReason::Moves75 => Failure::ExceededFiftyMoves,
+                _ => Failure::Abandoned,
+            }),
+        }
+    }
+
+    /// Number of moves played by the player since the start of the drill.
+    fn player_moves(&self, position: &Board) -> u32 {
+        let ply = |board: &Board| {
+            2 * board.raw().move_number as u32 + (board.side() == Color::Black) as u32
+        };
+        let plies = ply(position).saturating_sub(ply(&self.start));
+        if self.start.side() == self.player {
+            plies.div_ceil(2)
+        } else {
+            plies / 2
+        }
+    }
+}
+
+/// Material of `color` minus the material of its opponent, kings excepted.
+fn balance(board: &Board, color: Color) -> i32 {
+    let material = |color| {
+        [
+            (Piece::Queen, 9),
+            (Piece::Rook, 5),
+            (Piece::Bishop, 3),
+            (Piece::Knight, 3),
+            (Piece::Pawn, 1),
+        ]
+        .into_iter()
+        .map(|(piece, value)| board.piece2(color, piece).len() as i32 * value)
+        .sum::<i32>()
+    };
+    material(color) - material(color.inv())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KQK: &str = "8/8/8/4k3/8/8/8/4K2Q w - - 0 1";
+    const KQKR: &str = "8/8/8/4k3/8/3r4/8/4K2Q w - - 0 1";
+    const BLACK_MATED: &str = "7k/6Q1/6K1/8/8/8/8/8 b - - 0 2";
+    const BLACK_STALEMATED: &str = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 2";
+    const BARE_KINGS: &str = "8/8/8/4k3/8/8/8/4K3 w - - 0 2";
+
+    fn judge(goal: Goal, player: Color, start: &str) -> Judge {
+        Judge::new(goal, player, &Board::from_fen(start).unwrap())
+    }
+
+    fn verdict(judge: &Judge, fen: &str) -> Option<Verdict> {
+        let position = Board::from_fen(fen).unwrap();
+        judge.judge(&position, position.calc_outcome())
+    }
+
+    #[test]
+    fn mate() {
+        let judge = judge(Goal::Mate, Color::White, KQK);
+        assert_eq!(verdict(&judge, "8/8/3k4/8/8/8/8/4K2Q b - - 1 1"), None);
+        assert_eq!(verdict(&judge, BLACK_MATED), Some(Verdict::Success));
+        assert_eq!(
+            verdict(&judge, BARE_KINGS),
+            Some(Verdict::Failed(Failure::InsufficientMaterial))
+        );
+    }
+
+    #[test]
+    fn mate_within() {
+        let judge = judge(Goal::MateWithin(2), Color::White, KQK);
+        assert_eq!(verdict(&judge, "8/8/3k4/8/8/8/8/3K3Q w - - 2 2"), None);
+        assert_eq!(verdict(&judge, BLACK_MATED), Some(Verdict::Success));
+        assert_eq!(
+            verdict(&judge, "8/8/3k4/8/8/8/8/3K3Q w - - 4 3"),
+            Some(Verdict::Failed(Failure::TooSlow(2)))
+        );
+    }
+
+    #[test]
+    fn mate_within_counts_the_player_moves_only() {
+        let judge = judge(
+            Goal::MateWithin(1),
+            Color::White,
+            "8/8/8/4k3/8/8/8/4K2Q b - - 0 1",
+        );
+        assert_eq!(verdict(&judge, "8/8/3k4/8/8/8/8/4K2Q w - - 1 2"), None);
+        assert_eq!(
+            verdict(&judge, "8/8/8/4k3/8/8/8/3K3Q b - - 2 2"),
+            Some(Verdict::Failed(Failure::TooSlow(1)))
+        );
+    }
+
+    #[test]
+    fn win_material() {
+        let judge = judge(Goal::WinMaterial, Color::White, KQKR);
+        assert_eq!(verdict(&judge, "8/8/8/4k3/8/3Q4/8/4K3 b - - 0 1"), None);
+        assert_eq!(
+            verdict(&judge, "8/8/5k2/8/8/3Q4/8/4K3 w - - 1 2"),
+            Some(Verdict::Success)
+        );
+        assert_eq!(
+            verdict(&judge, BARE_KINGS),
+            Some(Verdict::Failed(Failure::InsufficientMaterial))
+        );
+    }
+
+    #[test]
+    fn draw() {
+        let judge = judge(Goal::Draw, Color::Black, KQK);
+        assert_eq!(verdict(&judge, "8/8/3k4/8/8/8/8/4K2Q w - - 1 2"), None);
+        assert_eq!(verdict(&judge, BARE_KINGS), Some(Verdict::Success));
+        assert_eq!(
+            verdict(&judge, BLACK_MATED),
+            Some(Verdict::Failed(Failure::Checkmated))
+        );
+    }
+
+    #[test]
+    fn stalemate() {
+        for goal in [Goal::Mate, Goal::MateWithin(10), Goal::WinMaterial] {
+            let judge = judge(goal, Color::White, KQK);
+            assert_eq!(
+                verdict(&judge, BLACK_STALEMATED),
+                Some(Verdict::Failed(Failure::Stalemated)),
+                "{}",
+                goal
+            );
+        }
+        let judge = judge(Goal::Draw, Color::Black, KQK);
+        assert_eq!(verdict(&judge, BLACK_STALEMATED), Some(Verdict::Success));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod catalogue;
+pub mod generator;
+pub mod goal;
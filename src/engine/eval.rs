@@ -0,0 +1,115 @@
+use owlchess::{Board, Color, Coord, Piece};
+
+use crate::endgames::generator::{distance, light_squares};
+
+/// Score of a checkmate at the root. Mates found deeper get smaller scores.
+pub const MATE_SCORE: i32 = 30_000;
+/// Scores above this value (in absolute value) are mate scores.
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1_000;
+
+pub fn piece_value(piece: Piece) -> i32 {
+    match piece {
+        Piece::Pawn => 100,
+        Piece::Knight => 320,
+        Piece::Bishop => 330,
+        Piece::Rook => 500,
+        Piece::Queen => 900,
+        Piece::King => 0,
+    }
+}
+
+/// Evaluates the position from the point of view of the side to move.
+pub fn evaluate(board: &Board) -> i32 {
+    let score = side_score(board, Color::White) - side_score(board, Color::Black);
+    match board.side() {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+fn material(board: &Board, color: Color) -> i32 {
+    [
+        Piece::Pawn,
+        Piece::Knight,
+        Piece::Bishop,
+        Piece::Rook,
+        Piece::Queen,
+    ]
+    .iter()
+    .map(|&piece| board.piece2(color, piece).len() as i32 * piece_value(piece))
+    .sum()
+}
+
+fn side_score(board: &Board, color: Color) -> i32 {
+    let own_material = material(board, color);
+    let other_material = material(board, color.inv());
+
+    let mut score = own_material + pawns_advance(board, color);
+
+    // Mating needs a clear material edge: then the winning king must come closer
+    // and the losing king must be driven to the edge.
+    let advantage = own_material - other_material;
+    let can_mate =
+        board.piece2(color, Piece::Pawn).is_nonempty() || own_material >= piece_value(Piece::Rook);
+    if advantage >= piece_value(Piece::Rook) - piece_value(Piece::Pawn) && can_mate {
+        let own_king = board.king_pos(color);
+        let other_king = board.king_pos(color.inv());
+        score += 10 * edge_closeness(board, color, other_king);
+        score += 4 * (14 - manhattan_distance(own_king, other_king) as i32);
+    } else {
+        score += 2 * (6 - center_distance(board.king_pos(color)) as i32);
+    }
+    score
+}
+
+fn pawns_advance(board: &Board, color: Color) -> i32 {
+    board
+        .piece2(color, Piece::Pawn)
+        .into_iter()
+        .map(|pawn| {
+            let advance = match color {
+                Color::White => 6 - pawn.rank().index() as i32,
+                Color::Black => pawn.rank().index() as i32 - 1,
+            };
+            advance * advance * 5
+        })
+        .sum()
+}
+
+/// How close the king is to the place it can be mated in: the edge in general, or the
+/// corners of the bishop's color when the attacker mates with bishop and knight.
+fn edge_closeness(board: &Board, attacker: Color, king: Coord) -> i32 {
+    let bishops = board.piece2(attacker, Piece::Bishop);
+    let single_bishop_mate = bishops.len() == 1
+        && board.piece2(attacker, Piece::Knight).len() == 1
+        && (board.piece2(attacker, Piece::Queen) | board.piece2(attacker, Piece::Rook)).is_empty();
+    if single_bishop_mate {
+        let light_bishop = (bishops & light_squares()).is_nonempty();
+        let corners = if light_bishop {
+            [Coord::from_index(0), Coord::from_index(63)]
+        } else {
+            [Coord::from_index(7), Coord::from_index(56)]
+        };
+        let to_corner = corners
+            .iter()
+            .map(|&corner| distance(king, corner))
+            .min()
+            .unwrap_or_default();
+        return 2 * (7 - to_corner as i32);
+    }
+    center_distance(king) as i32
+}
+
+/// Manhattan distance to the four central squares, from 0 to 6.
+fn center_distance(coord: Coord) -> usize {
+    let file = coord.file().index();
+    let rank = coord.rank().index();
+    let file_distance = if file < 4 { 3 - file } else { file - 4 };
+    let rank_distance = if rank < 4 { 3 - rank } else { rank - 4 };
+    file_distance + rank_distance
+}
+
+fn manhattan_distance(first: Coord, second: Coord) -> usize {
+    first.file().index().abs_diff(second.file().index())
+        + first.rank().index().abs_diff(second.rank().index())
+}
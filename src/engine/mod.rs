@@ -0,0 +1,151 @@
+use std::{
+    fmt,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+};
+
+use owlchess::{Board, Make, Move};
+
+use self::uci::UciError;
+
+mod eval;
+pub mod search;
+pub mod tablebase;
+pub mod uci;
+
+/// Anything able to choose the computer's move in a position.
+pub trait Engine: Send {
+    /// Chooses the move to play after `moves` have been played from `start`, the history
+    /// letting the engine see repetitions.
+    fn best_move(&mut self, start: &Board, moves: &[Move]) -> Result<Option<Move>, EngineError>;
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    Uci(UciError),
+    /// One of the moves sent to the engine is illegal.
+    IllegalHistory,
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uci(err) => write!(f, "{}", err),
+            Self::IllegalHistory => write!(f, "the moves of the game are not legal"),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<UciError> for EngineError {
+    fn from(err: UciError) -> Self {
+        Self::Uci(err)
+    }
+}
+
+/// Position reached by playing `moves` from `start`, or `None` if one of them is illegal.
+pub fn replay(start: &Board, moves: &[Move]) -> Option<Board> {
+    let mut position = start.clone();
+    for mv in moves {
+        position = mv.make(&position).ok()?;
+    }
+    Some(position)
+}
+
+/// What the background thread of a [`ComputerPlayer`] sends back.
+enum Answer {
+    /// The engine is ready, or could not be started.
+    Started(Result<(), EngineError>),
+    /// The searched position and the chosen move.
+    Reply(Result<(Board, Option<Move>), EngineError>),
+}
+
+/// Runs an [`Engine`] on a background thread, so that the frame loop never waits for it.
+pub struct ComputerPlayer {
+    requests: Sender<(Board, Vec<Move>)>,
+    answers: Receiver<Answer>,
+    /// Whether the engine is still being started.
+    starting: bool,
+    /// Positions sent to the engine and not answered yet.
+    pending: usize,
+}
+
+impl ComputerPlayer {
+    /// Starts the engine made by `start_engine`, such as an external process, on the
+    /// background thread too.
+    pub fn new<F>(start_engine: F) -> Self
+    where
+        F: FnOnce() -> Result<Box<dyn Engine>, EngineError> + Send + 'static,
+    {
+        let (requests, games) = mpsc::channel::<(Board, Vec<Move>)>();
+        let (answers_sender, answers) = mpsc::channel();
+        thread::spawn(move || {
+            let mut engine = match start_engine() {
+                Ok(engine) => engine,
+                Err(err) => {
+                    let _ = answers_sender.send(Answer::Started(Err(err)));
+                    return;
+                }
+            };
+            if answers_sender.send(Answer::Started(Ok(()))).is_err() {
+                return;
+            }
+            for (start, moves) in games {
+                let reply = match replay(&start, &moves) {
+                    Some(position) => engine
+                        .best_move(&start, &moves)
+                        .map(|best_move| (position, best_move)),
+                    // Still answered, so that the request is not left pending.
+                    None => Err(EngineError::IllegalHistory),
+                };
+                if answers_sender.send(Answer::Reply(reply)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            requests,
+            answers,
+            starting: true,
+            pending: 0,
+        }
+    }
+
+    /// Whether an answer is expected, the engine being started or searching.
+    pub fn is_thinking(&self) -> bool {
+        self.starting || self.pending > 0
+    }
+
+    /// Asks for the move to play after `moves` have been played from `start`.
+    pub fn start_thinking(&mut self, start: Board, moves: Vec<Move>) {
+        if self.requests.send((start, moves)).is_ok() {
+            self.pending += 1;
+        }
+    }
+
+    /// Returns the searched position and the chosen move, once the engine has answered,
+    /// or the error which kept it from starting or searching.
+    pub fn poll(&mut self) -> Option<Result<(Board, Option<Move>), EngineError>> {
+        loop {
+            match self.answers.try_recv() {
+                Ok(Answer::Started(result)) => {
+                    self.starting = false;
+                    if let Err(err) = result {
+                        return Some(Err(err));
+                    }
+                }
+                Ok(Answer::Reply(reply)) => {
+                    self.pending = self.pending.saturating_sub(1);
+                    return Some(reply);
+                }
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    self.starting = false;
+                    self.pending = 0;
+                    return None;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::time::{Duration, Instant};
+
+use owlchess::{movegen::semilegal, Board, Make, Move, MoveKind};
+
+use super::{
+    eval::{evaluate, piece_value, MATE_SCORE, MATE_THRESHOLD},
+    Engine, EngineError,
+};
+
+const TT_SIZE: usize = 1 << 20;
+/// How many nodes are searched between two checks of the clock.
+const NODES_BETWEEN_TIME_CHECKS: u64 = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TtEntry {
+    hash: u64,
+    depth: u8,
+    score: i32,
+    bound: Bound,
+    best_move: Option<Move>,
+}
+
+/// Fixed-size hash table of already searched positions, indexed by Zobrist hash.
+struct TranspositionTable {
+    entries: Vec<Option<TtEntry>>,
+}
+
+impl TranspositionTable {
+    fn new() -> Self {
+        Self {
+            entries: vec![None; TT_SIZE],
+        }
+    }
+
+    fn probe(&self, hash: u64) -> Option<TtEntry> {
+        self.entries[hash as usize % TT_SIZE].filter(|entry| entry.hash == hash)
+    }
+
+    fn store(&mut self, entry: TtEntry) {
+        let slot = &mut self.entries[entry.hash as usize % TT_SIZE];
+        let replace = match slot {
+            Some(old) => old.hash != entry.hash || old.depth <= entry.depth,
+            None => true,
+        };
+        if replace {
+            *slot = Some(entry);
+        }
+    }
+}
+
+/// Built-in iterative deepening alpha-beta search.
+pub struct Search {
+    tt: TranspositionTable,
+    time_limit: Duration,
+    max_depth: u8,
+    started_at: Instant,
+    nodes: u64,
+    aborted: bool,
+    /// Hashes of the positions on the current search path, for repetition detection.
+    path: Vec<u64>,
+}
+
+impl Search {
+    pub fn new(time_limit: Duration, max_depth: u8) -> Self {
+        Self {
+            tt: TranspositionTable::new(),
+            time_limit,
+            max_depth,
+            started_at: Instant::now(),
+            nodes: 0,
+            aborted: false,
+            path: vec![],
+        }
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        if self.nodes.is_multiple_of(NODES_BETWEEN_TIME_CHECKS)
+            && self.started_at.elapsed() >= self.time_limit
+        {
+            self.aborted = true;
+        }
+        self.aborted
+    }
+
+    fn alpha_beta(&mut self, board: &Board, depth: u8, mut alpha: i32, beta: i32, ply: i32) -> i32 {
+        self.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+
+        let hash = board.zobrist_hash();
+        if ply > 0 && (self.path.contains(&hash) || board.calc_draw_simple().is_some()) {
+            return 0;
+        }
+
+        let tt_entry = self.tt.probe(hash);
+        if let Some(entry) = tt_entry {
+            if ply > 0 && entry.depth >= depth {
+                let score = score_from_tt(entry.score, ply);
+                let cutoff = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if cutoff {
+                    return score;
+                }
+            }
+        }
+
+        if depth == 0 {
+            return self.quiescence(board, alpha, beta);
+        }
+
+        let moves = ordered_moves(board, tt_entry.and_then(|entry| entry.best_move));
+        let original_alpha = alpha;
+        let mut best_score = -MATE_SCORE;
+        let mut best_move = None;
+        let mut has_legal_move = false;
+
+        self.path.push(hash);
+        for mv in moves {
+            let child = match mv.make(board) {
+                Ok(child) => child,
+                Err(_) => continue,
+            };
+            has_legal_move = true;
+            let score = -self.alpha_beta(&child, depth - 1, -beta, -alpha, ply + 1);
+            if self.aborted {
+                break;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        self.path.pop();
+
+        if self.aborted {
+            return 0;
+        }
+        if !has_legal_move {
+            return if board.is_check() {
+                -MATE_SCORE + ply
+            } else {
+                0
+            };
+        }
+
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.tt.store(TtEntry {
+            hash,
+            depth,
+            score: score_to_tt(best_score, ply),
+            bound,
+            best_move,
+        });
+        best_score
+    }
+
+    fn quiescence(&mut self, board: &Board, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+
+        let stand_pat = evaluate(board);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut captures = semilegal::gen_capture(board).to_vec();
+        captures.sort_by_key(|&mv| -capture_gain(board, mv));
+        for mv in captures {
+            let child = match mv.make(board) {
+                Ok(child) => child,
+                Err(_) => continue,
+            };
+            let score = -self.quiescence(&child, -beta, -alpha);
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
+
+impl Engine for Search {
+    fn best_move(&mut self, start: &Board, moves: &[Move]) -> Result<Option<Move>, EngineError> {
+        self.started_at = Instant::now();
+        self.nodes = 0;
+        self.aborted = false;
+        // The positions of the game count for repetitions, as the ones of the search path.
+        self.path.clear();
+        let mut position = start.clone();
+        for mv in moves {
+            self.path.push(position.zobrist_hash());
+            position = mv
+                .make(&position)
+                .map_err(|_| EngineError::IllegalHistory)?;
+        }
+        let board = &position;
+
+        let mut best_move = None;
+        for depth in 1..=self.max_depth {
+            let score = self.alpha_beta(board, depth, -MATE_SCORE, MATE_SCORE, 0);
+            if self.aborted {
+                break;
+            }
+            best_move = self
+                .tt
+                .probe(board.zobrist_hash())
+                .and_then(|entry| entry.best_move);
+            if score.abs() >= MATE_THRESHOLD {
+                break;
+            }
+        }
+
+        // Not even the first iteration could complete: play any legal move.
+        Ok(best_move.or_else(|| {
+            semilegal::gen_all(board)
+                .iter()
+                .copied()
+                .find(|mv| mv.make(board).is_ok())
+        }))
+    }
+}
+
+/// Hash move first, then captures by most valuable victim and promotions.
+fn ordered_moves(board: &Board, hash_move: Option<Move>) -> Vec<Move> {
+    let mut moves = semilegal::gen_all(board).to_vec();
+    moves.sort_by_key(|&mv| {
+        if Some(mv) == hash_move {
+            i32::MIN
+        } else {
+            -capture_gain(board, mv)
+        }
+    });
+    moves
+}
+
+fn capture_gain(board: &Board, mv: Move) -> i32 {
+    let victim = board
+        .get(mv.dst())
+        .piece()
+        .map(piece_value)
+        .unwrap_or_default();
+    let attacker = board
+        .get(mv.src())
+        .piece()
+        .map(piece_value)
+        .unwrap_or_default();
+    let promotion = match mv.kind() {
+        MoveKind::PromoteQueen => piece_value(owlchess::Piece::Queen),
+        _ => 0,
+    };
+    if victim > 0 {
+        10 * victim - attacker / 10 + promotion
+    } else {
+        promotion
+    }
+}
+
+/// Mate scores are stored relative to the node, so that they stay valid at another ply.
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score + ply
+    } else if score <= -MATE_THRESHOLD {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score - ply
+    } else if score <= -MATE_THRESHOLD {
+        score + ply
+    } else {
+        score
+    }
+}
@@ -0,0 +1,34 @@
+use owlchess::{Board, Move};
+
+use super::{replay, Engine, EngineError};
+use crate::tablebase::Tablebase;
+
+/// Plays the tablebase move whenever the material is covered, and asks
+/// the fallback engine otherwise.
+pub struct TablebaseEngine {
+    tablebases: Vec<Box<dyn Tablebase>>,
+    fallback: Box<dyn Engine>,
+}
+
+impl TablebaseEngine {
+    pub fn new(tablebases: Vec<Box<dyn Tablebase>>, fallback: Box<dyn Engine>) -> Self {
+        Self {
+            tablebases,
+            fallback,
+        }
+    }
+}
+
+impl Engine for TablebaseEngine {
+    fn best_move(&mut self, start: &Board, moves: &[Move]) -> Result<Option<Move>, EngineError> {
+        let board = replay(start, moves).ok_or(EngineError::IllegalHistory)?;
+        let tablebase_move = self
+            .tablebases
+            .iter()
+            .find_map(|tablebase| tablebase.probe(&board).and_then(|probe| probe.best_move));
+        match tablebase_move {
+            Some(tablebase_move) => Ok(Some(tablebase_move)),
+            None => self.fallback.best_move(start, moves),
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use owlchess::{Board, Move};
+
+use super::{replay, Engine, EngineError};
+
+/// How long the engine may take to answer `uci` and `isready`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Extra time given to the engine on top of the requested move time.
+const MOVE_TIME_MARGIN: Duration = Duration::from_secs(5);
+/// How long the engine may take to exit after `quit` before being killed.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum UciError {
+    Spawn(io::Error),
+    Io(io::Error),
+    Timeout(&'static str),
+    EngineExited,
+    InvalidBestMove(String),
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spawn(err) => write!(f, "cannot start engine : {}", err),
+            Self::Io(err) => write!(f, "cannot talk to engine : {}", err),
+            Self::Timeout(expected) => write!(f, "engine did not answer {} in time", expected),
+            Self::EngineExited => write!(f, "engine exited unexpectedly"),
+            Self::InvalidBestMove(best_move) => {
+                write!(f, "engine played an invalid move : {}", best_move)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UciError {}
+
+impl From<io::Error> for UciError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// External engine speaking the UCI protocol over its standard input and output.
+pub struct UciEngine {
+    process: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    move_time: Duration,
+    handshake_timeout: Duration,
+    move_time_margin: Duration,
+}
+
+impl UciEngine {
+    /// Spawns the engine executable and performs the `uci`/`isready` handshake.
+    pub fn start(path: impl AsRef<Path>, move_time: Duration) -> Result<Self, UciError> {
+        Self::launch(path, move_time, HANDSHAKE_TIMEOUT, MOVE_TIME_MARGIN)
+    }
+
+    /// Same as `start`, with the timeouts given instead of the default ones.
+    fn launch(
+        path: impl AsRef<Path>,
+        move_time: Duration,
+        handshake_timeout: Duration,
+        move_time_margin: Duration,
+    ) -> Result<Self, UciError> {
+        let mut process = Command::new(path.as_ref())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(UciError::Spawn)?;
+        let stdin = process.stdin.take().ok_or(UciError::EngineExited)?;
+        let stdout = process.stdout.take().ok_or(UciError::EngineExited)?;
+
+        // Reading happens on its own thread, so that every wait can time out.
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let mut engine = Self {
+            process,
+            stdin,
+            lines,
+            move_time,
+            handshake_timeout,
+            move_time_margin,
+        };
+        engine.send("uci")?;
+        engine.wait_for("uciok", handshake_timeout)?;
+        engine.send("ucinewgame")?;
+        engine.send("isready")?;
+        engine.wait_for("readyok", handshake_timeout)?;
+        Ok(engine)
+    }
+
+    /// Asks the engine for its move after `moves` have been played from `start`.
+    pub fn search(&mut self, start: &Board, moves: &[Move]) -> Result<Option<Move>, EngineError> {
+        let position = replay(start, moves).ok_or(EngineError::IllegalHistory)?;
+
+        self.send(&position_command(start, moves))?;
+        self.send(&format!("go movetime {}", self.move_time.as_millis()))?;
+        let line = match self.wait_for("bestmove", self.move_time + self.move_time_margin) {
+            Ok(line) => line,
+            Err(UciError::Timeout(expected)) => {
+                self.abandon_search()?;
+                return Err(UciError::Timeout(expected).into());
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let best_move = match line.split_whitespace().nth(1) {
+            Some(best_move) => best_move,
+            None => return Err(UciError::InvalidBestMove(line).into()),
+        };
+        if best_move == "(none)" || best_move == "0000" {
+            return Ok(None);
+        }
+        // Parsing alone accepts any move of a piece, such as a queen jumping over pawns.
+        Move::from_uci_legal(best_move, &position)
+            .map(Some)
+            .map_err(|_| UciError::InvalidBestMove(best_move.to_string()).into())
+    }
+
+    /// Stops a search which took too long, so that its late `bestmove` is not read as the
+    /// answer to the next one.
+    fn abandon_search(&mut self) -> Result<(), UciError> {
+        self.send("stop")?;
+        // An engine not answering `stop` is still brought back in line by `isready`.
+        let _ = self.wait_for("bestmove", self.handshake_timeout);
+        self.send("isready")?;
+        self.wait_for("readyok", self.handshake_timeout)?;
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), UciError> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Skips the engine output until a line starting with `expected` comes, and returns it.
+    fn wait_for(&mut self, expected: &'static str, timeout: Duration) -> Result<String, UciError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(remaining) {
+                Ok(line) => {
+                    if line.split_whitespace().next() == Some(expected) {
+                        return Ok(line);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => return Err(UciError::Timeout(expected)),
+                Err(RecvTimeoutError::Disconnected) => return Err(UciError::EngineExited),
+            }
+        }
+    }
+}
+
+impl Engine for UciEngine {
+    fn best_move(&mut self, start: &Board, moves: &[Move]) -> Result<Option<Move>, EngineError> {
+        self.search(start, moves)
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.process.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+fn position_command(start: &Board, moves: &[Move]) -> String {
+    let mut command = format!("position fen {}", start.as_fen());
+    if !moves.is_empty() {
+        command.push_str(" moves");
+        for mv in moves {
+            command.push(' ');
+            command.push_str(&mv.uci().to_string());
+        }
+    }
+    command
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf, sync::Mutex};
+
+    use super::*;
+
+    /// Writing a script while another test forks could leave it busy, so they run one by one.
+    static SCRIPTS: Mutex<()> = Mutex::new(());
+
+    const MOVE_TIME: Duration = Duration::from_millis(50);
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Stand-in engine: a shell script answering `uci` with `on_uci` and the `go` commands with
+    /// `on_go`, where `$searches` counts them. `on_line` runs first for every line received.
+    struct ScriptedEngine {
+        path: PathBuf,
+    }
+
+    impl ScriptedEngine {
+        fn new(name: &str, on_uci: &str, on_go: &str, on_line: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "scripted-uci-{}-{}.sh",
+                std::process::id(),
+                name
+            ));
+            let script = format!(
+                "#!/bin/sh\n\
+                 searches=0\n\
+                 while read -r line; do\n\
+                 {}\n\
+                 case \"$line\" in\n\
+                 uci) {} ;;\n\
+                 isready) echo readyok ;;\n\
+                 go*) searches=$((searches + 1)); {} ;;\n\
+                 quit) exit 0 ;;\n\
+                 esac\n\
+                 done\n",
+                on_line, on_uci, on_go
+            );
+            fs::write(&path, script).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            Self { path }
+        }
+
+        /// A well-behaved engine playing `best_move`.
+        fn playing(name: &str, best_move: &str) -> Self {
+            let on_go = format!(
+                "echo 'info depth 1 score cp 20'; echo 'bestmove {}'",
+                best_move
+            );
+            Self::new(name, "echo 'id name Scripted'; echo uciok", &on_go, ":")
+        }
+
+        fn start(&self) -> Result<UciEngine, UciError> {
+            UciEngine::launch(&self.path, MOVE_TIME, TIMEOUT, TIMEOUT)
+        }
+    }
+
+    impl Drop for ScriptedEngine {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    fn lock() -> std::sync::MutexGuard<'static, ()> {
+        SCRIPTS.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn uci_move(text: &str, position: &Board) -> Move {
+        Move::from_uci_legal(text, position).unwrap()
+    }
+
+    #[test]
+    fn handshake_then_best_move() {
+        let _lock = lock();
+        let script = ScriptedEngine::playing("best-move", "e2e4 ponder e7e5");
+        let mut engine = script.start().unwrap();
+        let start = Board::initial();
+        let best_move = engine.search(&start, &[]).unwrap();
+        assert_eq!(best_move, Some(uci_move("e2e4", &start)));
+    }
+
+    #[test]
+    fn best_move_after_the_game_moves() {
+        let _lock = lock();
+        let script = ScriptedEngine::playing("after-moves", "g8f6");
+        let mut engine = script.start().unwrap();
+        let start = Board::initial();
+        let moves = [uci_move("e2e4", &start)];
+        let position = start.make_move(moves[0]).unwrap();
+        let best_move = engine.search(&start, &moves).unwrap();
+        assert_eq!(best_move, Some(uci_move("g8f6", &position)));
+    }
+
+    #[test]
+    fn no_move_in_a_finished_game() {
+        let _lock = lock();
+        let script = ScriptedEngine::playing("no-move", "(none)");
+        let mut engine = script.start().unwrap();
+        assert_eq!(engine.search(&Board::initial(), &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn handshake_timeout() {
+        let _lock = lock();
+        let script = ScriptedEngine::new("silent", "echo 'id name Scripted'", ":", ":");
+        assert!(matches!(script.start(), Err(UciError::Timeout("uciok"))));
+    }
+
+    #[test]
+    fn late_best_move_is_not_taken_for_the_next_one() {
+        let _lock = lock();
+        // The first search answers too late, once the next command comes.
+        let script = ScriptedEngine::new(
+            "late",
+            "echo uciok",
+            "if [ $searches = 1 ]; then late=1; else echo 'bestmove e2e4'; fi",
+            "if [ \"$late\" = 1 ]; then echo 'bestmove a2a3'; late=0; fi",
+        );
+        let mut engine = script.start().unwrap();
+        let start = Board::initial();
+        assert!(matches!(
+            engine.search(&start, &[]),
+            Err(EngineError::Uci(UciError::Timeout("bestmove")))
+        ));
+        let best_move = engine.search(&start, &[]).unwrap();
+        assert_eq!(best_move, Some(uci_move("e2e4", &start)));
+    }
+
+    #[test]
+    fn illegal_best_move() {
+        let _lock = lock();
+        // Unreachable squares, then pseudo-moves which only parsing would accept.
+        for best_move in ["e2e5", "d1h5", "e1e3", "g1g3"] {
+            let script = ScriptedEngine::playing("illegal", best_move);
+            let mut engine = script.start().unwrap();
+            match engine.search(&Board::initial(), &[]) {
+                Err(EngineError::Uci(UciError::InvalidBestMove(answer))) => {
+                    assert_eq!(answer, best_move)
+                }
+                other => panic!("unexpected answer to {} : {:?}", best_move, other),
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_history() {
+        let _lock = lock();
+        let script = ScriptedEngine::playing("history", "e7e5");
+        let mut engine = script.start().unwrap();
+        let start = Board::initial();
+        let after_e4 = start.make_move(uci_move("e2e4", &start)).unwrap();
+        // Black's move, sent as if it came first.
+        let moves = [uci_move("e7e5", &after_e4)];
+        assert!(matches!(
+            engine.search(&start, &moves),
+            Err(EngineError::IllegalHistory)
+        ));
+    }
+
+    #[test]
+    fn position_command_lists_the_moves() {
+        let start = Board::initial();
+        let moves = [uci_move("e2e4", &start)];
+        assert_eq!(
+            position_command(&start, &moves),
+            format!("position fen {} moves e2e4", start.as_fen())
+        );
+    }
+}
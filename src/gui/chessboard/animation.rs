@@ -0,0 +1,81 @@
+use std::time::{Duration, Instant};
+
+use owlchess::{Board, Color, Coord, File, Move, MoveKind, Piece};
+
+pub(crate) struct AnimatedPiece {
+    pub(crate) piece: Piece,
+    pub(crate) color: Color,
+    pub(crate) from: Coord,
+    pub(crate) to: Coord,
+}
+
+/// Pieces sliding from their start cell to their target cell after a move, the captured one fading.
+pub(crate) struct MoveAnimation {
+    start: Instant,
+    /// The moved piece, then the rook when castling.
+    pub(crate) movements: Vec<AnimatedPiece>,
+    /// The captured piece stays on its cell, `from` and `to` being the same.
+    pub(crate) captured: Option<AnimatedPiece>,
+}
+
+impl MoveAnimation {
+    /// Animation of `mv`, played in `position`.
+    pub(crate) fn new(position: &Board, mv: Move) -> Option<Self> {
+        let moved = position.get(mv.src());
+        let mut movements = vec![AnimatedPiece {
+            piece: moved.piece()?,
+            color: moved.color()?,
+            from: mv.src(),
+            to: mv.dst(),
+        }];
+
+        let rook_files = match mv.kind() {
+            MoveKind::CastlingKingside => Some((File::H, File::F)),
+            MoveKind::CastlingQueenside => Some((File::A, File::D)),
+            _ => None,
+        };
+        if let Some((from_file, to_file)) = rook_files {
+            movements.push(AnimatedPiece {
+                piece: Piece::Rook,
+                color: position.side(),
+                from: Coord::from_parts(from_file, mv.src().rank()),
+                to: Coord::from_parts(to_file, mv.src().rank()),
+            });
+        }
+
+        let captured_cell = match mv.kind() {
+            MoveKind::Enpassant => Coord::from_parts(mv.dst().file(), mv.src().rank()),
+            _ => mv.dst(),
+        };
+        let captured = position.get(captured_cell);
+        let captured = match (captured.piece(), captured.color()) {
+            (Some(piece), Some(color)) if color != position.side() => Some(AnimatedPiece {
+                piece,
+                color,
+                from: captured_cell,
+                to: captured_cell,
+            }),
+            _ => None,
+        };
+
+        Some(Self {
+            start: Instant::now(),
+            movements,
+            captured,
+        })
+    }
+
+    /// Part of the animation already played, from 0 to 1, eased so that the pieces slow down.
+    pub(crate) fn progress(&self, duration: Duration) -> f32 {
+        if duration.is_zero() {
+            return 1.0;
+        }
+        let linear = (self.start.elapsed().as_secs_f32() / duration.as_secs_f32()).min(1.0);
+        linear * (2.0 - linear)
+    }
+
+    /// Cells the moving pieces arrive on, where the position already has them.
+    pub(crate) fn covers(&self, cell: Coord) -> bool {
+        self.movements.iter().any(|movement| movement.to == cell)
+    }
+}
@@ -0,0 +1,107 @@
+use eframe::egui::Modifiers;
+use owlchess::Coord;
+
+/// Colours of the arrows and circles drawn by the user, as they are named in PGN commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationColor {
+    Green,
+    Red,
+    Blue,
+    Yellow,
+}
+
+impl AnnotationColor {
+    /// Colour chosen by the modifier keys held: none for green, Shift for red, Alt for blue,
+    /// and both for yellow.
+    pub(crate) fn from_modifiers(modifiers: Modifiers) -> Self {
+        match (modifiers.shift, modifiers.alt) {
+            (false, false) => Self::Green,
+            (true, false) => Self::Red,
+            (false, true) => Self::Blue,
+            (true, true) => Self::Yellow,
+        }
+    }
+
+    fn pgn_letter(self) -> char {
+        match self {
+            Self::Green => 'G',
+            Self::Red => 'R',
+            Self::Blue => 'B',
+            Self::Yellow => 'Y',
+        }
+    }
+}
+
+/// Arrows and circled cells drawn by the user on a position.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Annotations {
+    arrows: Vec<(AnnotationColor, Coord, Coord)>,
+    circles: Vec<(AnnotationColor, Coord)>,
+}
+
+impl Annotations {
+    /// Draws an arrow, or removes it if the same one is already there.
+    pub(crate) fn toggle_arrow(&mut self, color: AnnotationColor, from: Coord, to: Coord) {
+        let existing = self
+            .arrows
+            .iter()
+            .position(|&(_, other_from, other_to)| (other_from, other_to) == (from, to));
+        match existing {
+            Some(index) if self.arrows[index].0 == color => {
+                self.arrows.remove(index);
+            }
+            Some(index) => self.arrows[index].0 = color,
+            None => self.arrows.push((color, from, to)),
+        }
+    }
+
+    /// Circles a cell, or removes the circle if the same one is already there.
+    pub(crate) fn toggle_circle(&mut self, color: AnnotationColor, cell: Coord) {
+        let existing = self
+            .circles
+            .iter()
+            .position(|&(_, other_cell)| other_cell == cell);
+        match existing {
+            Some(index) if self.circles[index].0 == color => {
+                self.circles.remove(index);
+            }
+            Some(index) => self.circles[index].0 = color,
+            None => self.circles.push((color, cell)),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.arrows.clear();
+        self.circles.clear();
+    }
+
+    pub(crate) fn arrows(&self) -> &[(AnnotationColor, Coord, Coord)] {
+        &self.arrows
+    }
+
+    pub(crate) fn circles(&self) -> &[(AnnotationColor, Coord)] {
+        &self.circles
+    }
+
+    /// PGN comment commands, such as "[%csl Gd4][%cal Re1e8,Gd2d4]", or `None` without annotations.
+    pub(crate) fn pgn_commands(&self) -> Option<String> {
+        let mut commands = String::new();
+        if !self.circles.is_empty() {
+            let circles: Vec<String> = self
+                .circles
+                .iter()
+                .map(|(color, cell)| format!("{}{}", color.pgn_letter(), cell))
+                .collect();
+            commands.push_str(&format!("[%csl {}]", circles.join(",")));
+        }
+        if !self.arrows.is_empty() {
+            let arrows: Vec<String> = self
+                .arrows
+                .iter()
+                .map(|(color, from, to)| format!("{}{}{}", color.pgn_letter(), from, to))
+                .collect();
+            commands.push_str(&format!("[%cal {}]", arrows.join(",")));
+        }
+        (!commands.is_empty()).then_some(commands)
+    }
+}
@@ -0,0 +1,61 @@
+use owlchess::{Board, DrawReason, Outcome};
+
+/// Keeps what is needed to tell whether the game on the board is over.
+///
+/// Checkmate, stalemate, insufficient material and the fifty-move rule only depend on the
+/// position, but threefold repetition needs every position reached since the game started.
+#[derive(Default)]
+pub(crate) struct GameState {
+    hashes: Vec<u64>,
+    outcome: Option<Outcome>,
+}
+
+impl GameState {
+    /// Starts a new game from `position`.
+    pub(crate) fn reset(&mut self, position: &Board) {
+        self.hashes.clear();
+        self.outcome = None;
+        self.record(position);
+    }
+
+    /// Records the position reached after a move, and returns the outcome if the game just ended.
+    pub(crate) fn record(&mut self, position: &Board) -> Option<Outcome> {
+        let hash = position.zobrist_hash();
+        self.hashes.push(hash);
+
+        self.outcome = position.calc_outcome().or_else(|| {
+            let repetitions = self.hashes.iter().filter(|&&other| other == hash).count();
+            (repetitions >= 3).then_some(Outcome::Draw(DrawReason::Repeat3))
+        });
+        self.outcome
+    }
+
+    /// Starts over from the positions of a game, such as after a move has been taken back.
+    pub(crate) fn replay<'a>(&mut self, positions: impl Iterator<Item = &'a Board>) {
+        self.hashes.clear();
+        self.outcome = None;
+        for position in positions {
+            self.record(position);
+        }
+    }
+
+    pub(crate) fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub(crate) fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+}
+
+/// Human readable description of a game result, such as "White wins by checkmate".
+pub fn describe_outcome(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Win { side, reason } => {
+            let mut winner = side.as_long_str().to_string();
+            winner[..1].make_ascii_uppercase();
+            format!("{} wins by {}", winner, reason)
+        }
+        Outcome::Draw(reason) => format!("Draw by {}", reason),
+    }
+}
@@ -0,0 +1,197 @@
+use eframe::egui::{self, ScrollArea, Ui};
+use owlchess::{Board, Color, Move};
+
+use super::annotations::Annotations;
+
+pub(crate) struct PlayedMove {
+    pub(crate) mv: Move,
+    pub(crate) san: String,
+    /// Position reached after the move.
+    pub(crate) position: Board,
+    /// Arrows and circles drawn on the position reached.
+    pub(crate) annotations: Annotations,
+}
+
+/// Moves played since the game started, one of which may be viewed instead of the live position.
+pub(crate) struct MoveHistory {
+    start: Board,
+    start_annotations: Annotations,
+    moves: Vec<PlayedMove>,
+    /// Moves taken back, the last one being the next to redo.
+    undone: Vec<PlayedMove>,
+    /// Number of moves played in the viewed position, `None` when the live position is shown.
+    viewed: Option<usize>,
+}
+
+impl MoveHistory {
+    pub(crate) fn new(start: Board) -> Self {
+        Self {
+            start,
+            start_annotations: Annotations::default(),
+            moves: vec![],
+            undone: vec![],
+            viewed: None,
+        }
+    }
+
+    pub(crate) fn start(&self) -> &Board {
+        &self.start
+    }
+
+    pub(crate) fn moves(&self) -> impl Iterator<Item = Move> + '_ {
+        self.moves.iter().map(|played| played.mv)
+    }
+
+    /// Number of moves played since the start, the ones taken back excepted.
+    pub(crate) fn ply(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Records a newly played move, which forgets the moves taken back.
+    pub(crate) fn push(&mut self, played: PlayedMove) {
+        self.moves.push(played);
+        self.undone.clear();
+    }
+
+    /// Takes back the last move, and returns the position before it.
+    pub(crate) fn undo(&mut self) -> Option<&Board> {
+        let played = self.moves.pop()?;
+        self.undone.push(played);
+        self.viewed = None;
+        Some(self.last_position())
+    }
+
+    /// Plays again the last move taken back, and returns the position after it.
+    pub(crate) fn redo(&mut self) -> Option<&Board> {
+        let played = self.undone.pop()?;
+        self.moves.push(played);
+        self.viewed = None;
+        Some(self.last_position())
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.moves.is_empty()
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    pub(crate) fn last_position(&self) -> &Board {
+        self.moves
+            .last()
+            .map_or(&self.start, |played| &played.position)
+    }
+
+    pub(crate) fn last_move(&self) -> Option<Move> {
+        self.moves.last().map(|played| played.mv)
+    }
+
+    /// Every position of the game, from the starting one.
+    pub(crate) fn positions(&self) -> impl Iterator<Item = &Board> {
+        std::iter::once(&self.start).chain(self.moves.iter().map(|played| &played.position))
+    }
+
+    pub(crate) fn is_viewing_past(&self) -> bool {
+        self.viewed.is_some()
+    }
+
+    pub(crate) fn view_live(&mut self) {
+        self.viewed = None;
+    }
+
+    /// The viewed position, if it is not the live one.
+    pub(crate) fn viewed_position(&self) -> Option<&Board> {
+        match self.viewed? {
+            0 => Some(&self.start),
+            ply => Some(&self.moves[ply - 1].position),
+        }
+    }
+
+    /// The move which led to the viewed position, if it is not the live one.
+    pub(crate) fn viewed_move(&self) -> Option<Option<Move>> {
+        match self.viewed? {
+            0 => Some(None),
+            ply => Some(Some(self.moves[ply - 1].mv)),
+        }
+    }
+
+    /// Annotations of the displayed position: the viewed one, or the live one.
+    pub(crate) fn annotations(&self) -> &Annotations {
+        let ply = self.viewed.unwrap_or(self.moves.len());
+        match ply {
+            0 => &self.start_annotations,
+            ply => &self.moves[ply - 1].annotations,
+        }
+    }
+
+    pub(crate) fn annotations_mut(&mut self) -> &mut Annotations {
+        let ply = self.viewed.unwrap_or(self.moves.len());
+        match ply {
+            0 => &mut self.start_annotations,
+            ply => &mut self.moves[ply - 1].annotations,
+        }
+    }
+
+    /// Annotations of every position of the game, from the starting one.
+    pub(crate) fn all_annotations(&self) -> impl Iterator<Item = &Annotations> {
+        std::iter::once(&self.start_annotations)
+            .chain(self.moves.iter().map(|played| &played.annotations))
+    }
+
+    /// Shows the moves by pairs, numbered as in the starting position. Clicking a move views it.
+    pub(crate) fn show(&mut self, ui: &mut Ui) -> egui::Response {
+        let mut clicked = None;
+        let response = ui
+            .vertical(|ui| {
+                ui.heading("Moves");
+                ScrollArea::vertical().show(ui, |ui| {
+                    let mut move_number = self.start.raw().move_number;
+                    let black_starts = self.start.side() == Color::Black;
+                    let mut ply = 0;
+                    while ply < self.moves.len() {
+                        ui.horizontal(|ui| {
+                            if ply == 0 && black_starts {
+                                ui.label(format!("{}...", move_number));
+                            } else {
+                                ui.label(format!("{}.", move_number));
+                                if self.show_move(ui, ply) {
+                                    clicked = Some(ply + 1);
+                                }
+                                ply += 1;
+                            }
+                            if ply < self.moves.len() {
+                                if self.show_move(ui, ply) {
+                                    clicked = Some(ply + 1);
+                                }
+                                ply += 1;
+                            }
+                        });
+                        move_number += 1;
+                    }
+                });
+                if self.is_viewing_past() && ui.button("Back to the game").clicked() {
+                    self.view_live();
+                }
+            })
+            .response;
+
+        if let Some(ply) = clicked {
+            self.viewed = if ply == self.moves.len() {
+                None
+            } else {
+                Some(ply)
+            };
+        }
+        response
+    }
+
+    /// Returns whether the move has been clicked.
+    fn show_move(&self, ui: &mut Ui, ply: usize) -> bool {
+        let played = &self.moves[ply];
+        let white_move = played.position.side() == Color::Black;
+        let fan = super::utils::san_to_fan(played.san.clone(), white_move);
+        let selected = self.viewed == Some(ply + 1);
+        ui.selectable_label(selected, fan).clicked()
+    }
+}
@@ -1,13 +1,40 @@
 use eframe::{
-    egui::{self, Rect, Ui},
+    egui::{self, Key, Modifiers, PointerButton, Rect, Ui, WidgetInfo, WidgetType},
     epaint::{Pos2, Vec2},
 };
 pub use eframe::epaint::Color32;
 
-use owlchess::{Board, Color, File, Make, Piece, Rank};
-
-use self::{pieces_images::PiecesImages, utils::get_uci_move_for};
+use owlchess::{
+    board::FenParseError, movegen::legal, Board, Cell, Color, Coord, File, Make, Move, Outcome,
+    Piece, Rank,
+};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
 
+use self::{
+    animation::MoveAnimation,
+    annotations::Annotations,
+    game_state::GameState,
+    history::{MoveHistory, PlayedMove},
+    move_entry::MoveEntry,
+    pieces_images::PiecesImages,
+    utils::get_uci_move_for,
+};
+pub use annotations::AnnotationColor;
+pub use game_state::describe_outcome;
+pub use move_entry::MoveEntryError;
+pub use move_event::MoveEvent;
+pub use overlays::{Arrow, SquareHighlight};
+
+mod animation;
+mod annotations;
+mod game_state;
+mod history;
+mod move_entry;
+mod move_event;
+mod overlays;
 mod painter;
 mod pieces_images;
 mod utils;
@@ -21,6 +48,23 @@ pub struct Colors {
     pub dnd_cross_cell: Color32,
     pub last_move_arrow: Color32,
     pub coordinates: Color32,
+    /// Arrows and circles drawn by the user, by annotation colour.
+    pub annotation_green: Color32,
+    pub annotation_red: Color32,
+    pub annotation_blue: Color32,
+    pub annotation_yellow: Color32,
+    /// Dots on the empty cells the selected or dragged piece can go to.
+    pub legal_move_dot: Color32,
+    /// Rings around the pieces the selected or dragged piece can capture.
+    pub legal_capture_ring: Color32,
+    /// Frame of the cell under the keyboard cursor, while the board has the focus.
+    pub keyboard_cursor: Color32,
+    /// Center of the gradient under a king in check.
+    pub check_gradient: Color32,
+    pub checkmate_marker: Color32,
+    pub stalemate_marker: Color32,
+    /// Start and target cells of the moves queued during the opponent's turn.
+    pub premove: Color32,
 }
 
 impl Default for Colors {
@@ -34,6 +78,17 @@ impl Default for Colors {
             dnd_cross_cell: Color32::from_rgb(255, 182, 193),
             last_move_arrow: Color32::from_rgb(35, 136, 210),
             coordinates: Color32::from_rgb(255, 220, 10),
+            annotation_green: Color32::from_rgba_unmultiplied(21, 120, 27, 200),
+            annotation_red: Color32::from_rgba_unmultiplied(136, 32, 32, 200),
+            annotation_blue: Color32::from_rgba_unmultiplied(0, 48, 136, 200),
+            annotation_yellow: Color32::from_rgba_unmultiplied(230, 143, 0, 200),
+            legal_move_dot: Color32::from_rgba_unmultiplied(30, 100, 30, 150),
+            legal_capture_ring: Color32::from_rgba_unmultiplied(178, 34, 34, 170),
+            keyboard_cursor: Color32::from_rgb(30, 144, 255),
+            check_gradient: Color32::from_rgb(255, 0, 0),
+            checkmate_marker: Color32::from_rgb(139, 0, 0),
+            stalemate_marker: Color32::from_rgb(105, 105, 105),
+            premove: Color32::from_rgba_unmultiplied(70, 130, 180, 190),
         }
     }
 }
@@ -71,10 +126,132 @@ impl Colors {
     pub fn set_coordinates(&mut self, color: Color32) {
         self.coordinates = color;
     }
+
+    pub fn set_annotation(&mut self, annotation: AnnotationColor, color: Color32) {
+        match annotation {
+            AnnotationColor::Green => self.annotation_green = color,
+            AnnotationColor::Red => self.annotation_red = color,
+            AnnotationColor::Blue => self.annotation_blue = color,
+            AnnotationColor::Yellow => self.annotation_yellow = color,
+        }
+    }
+
+    pub(crate) fn annotation(&self, annotation: AnnotationColor) -> Color32 {
+        match annotation {
+            AnnotationColor::Green => self.annotation_green,
+            AnnotationColor::Red => self.annotation_red,
+            AnnotationColor::Blue => self.annotation_blue,
+            AnnotationColor::Yellow => self.annotation_yellow,
+        }
+    }
+
+    pub fn set_legal_move_dots(&mut self, color: Color32) {
+        self.legal_move_dot = color;
+    }
+
+    pub fn set_legal_capture_rings(&mut self, color: Color32) {
+        self.legal_capture_ring = color;
+    }
+
+    pub fn set_keyboard_cursor(&mut self, color: Color32) {
+        self.keyboard_cursor = color;
+    }
+
+    pub fn set_check_gradient(&mut self, color: Color32) {
+        self.check_gradient = color;
+    }
+
+    pub fn set_checkmate_marker(&mut self, color: Color32) {
+        self.checkmate_marker = color;
+    }
+
+    pub fn set_stalemate_marker(&mut self, color: Color32) {
+        self.stalemate_marker = color;
+    }
+
+    pub fn set_premove_cells(&mut self, color: Color32) {
+        self.premove = color;
+    }
 }
 
 
 
+#[derive(Debug)]
+pub enum PositionError {
+    InvalidFen(FenParseError),
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFen(err) => write!(f, "invalid FEN : {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+impl From<FenParseError> for PositionError {
+    fn from(err: FenParseError) -> Self {
+        Self::InvalidFen(err)
+    }
+}
+
+/// Time taken by the pieces to slide to their target cell, unless changed.
+const DEFAULT_ANIMATION_DURATION: Duration = Duration::from_millis(250);
+
+/// How the pieces are shown, for visualisation exercises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PiecesDisplay {
+    Visible,
+    /// Blindfold: no piece is drawn.
+    Hidden,
+    /// Only the pieces of the given side, the player's one, are drawn.
+    HideOpponent(Color),
+    /// Every piece is drawn as a disc of its colour, without telling its kind.
+    Discs,
+    /// The pieces are shown for this long once the position is set, then hidden.
+    Flash(Duration),
+}
+
+/// Which sides the user plays on the board, the other ones being left to the computer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerMode {
+    WhiteHuman,
+    BlackHuman,
+    /// The user plays both sides, such as when analysing.
+    Both,
+    /// The user only watches, the computer playing both sides.
+    #[allow(dead_code)]
+    Observer,
+}
+
+impl PlayerMode {
+    /// Mode where the user plays `side` against the computer.
+    pub fn human(side: Color) -> Self {
+        match side {
+            Color::White => Self::WhiteHuman,
+            Color::Black => Self::BlackHuman,
+        }
+    }
+
+    pub fn is_human(self, side: Color) -> bool {
+        match self {
+            Self::WhiteHuman => side == Color::White,
+            Self::BlackHuman => side == Color::Black,
+            Self::Both => true,
+            Self::Observer => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PieceRendering {
+    Image,
+    Disc,
+    Hidden,
+}
+
 #[derive(Debug)]
 pub(crate) struct DndData {
     piece_type: Piece,
@@ -87,6 +264,19 @@ pub(crate) struct DndData {
     end_rank: u8,
     has_pending_promotion: bool,
     started_with_reversed_board: bool,
+    /// The piece is dragged during the opponent's turn, to queue a premove.
+    premove: bool,
+}
+
+/// Move queued during the opponent's turn, with cells as (file, rank).
+///
+/// Its legality is only known once it is the user's turn.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Premove {
+    pub(crate) from: (u8, u8),
+    pub(crate) to: (u8, u8),
+    /// Pawns reaching the last rank are promoted to a queen.
+    promotion: Option<char>,
 }
 
 pub struct ChessBoard {
@@ -95,26 +285,194 @@ pub struct ChessBoard {
     position: Board,
     reversed: bool,
     dnd_data: Option<DndData>,
-    last_move_arrow: Option<(u8, u8, u8, u8)>,
-    on_move_done: Box<dyn Fn(&String) -> ()>,
+    /// Cell of the piece selected by a click, as (file, rank), waiting for a click on its target.
+    selected_cell: Option<(u8, u8)>,
+    /// Cell where the right button was pressed, while the user draws an arrow.
+    annotation_start: Option<(u8, u8)>,
+    /// Cell of the keyboard cursor, as (file, rank), once it has been moved or clicked.
+    keyboard_cursor: Option<(u8, u8)>,
+    /// Moves to play in turn, as soon as the user is to move.
+    premoves: Vec<Premove>,
+    /// Arrows and highlighted cells added by the caller, drawn until cleared.
+    arrows: Vec<Arrow>,
+    highlights: Vec<SquareHighlight>,
+    on_move_done: Box<dyn Fn(&MoveEvent)>,
+    on_game_over: Box<dyn Fn(&Outcome)>,
+    on_computer_to_move: Box<dyn Fn(&Board)>,
+    player_mode: PlayerMode,
+    game_state: GameState,
+    history: MoveHistory,
+    move_entry: MoveEntry,
+    animation: Option<MoveAnimation>,
+    animation_duration: Duration,
+    pieces_display: PiecesDisplay,
+    /// When the pieces were last shown in the `Flash` display.
+    flash_start: Instant,
     colors: Colors,
 }
 
 impl ChessBoard {
-    pub fn new(size: f32, on_move_done: Box<dyn Fn(&String) -> ()>) -> Self {
+    /// Creates a board with the initial position, calling `on_move_done` after each move played.
+    pub fn new(size: f32, on_move_done: Box<dyn Fn(&MoveEvent)>) -> Self {
+        let position = Board::initial();
+        let mut game_state = GameState::default();
+        game_state.reset(&position);
         Self {
             size,
             pieces_images: PiecesImages::new(),
-            position: Board::initial(),
+            history: MoveHistory::new(position.clone()),
+            position,
             reversed: false,
             dnd_data: None,
+            selected_cell: None,
+            keyboard_cursor: None,
+            annotation_start: None,
+            premoves: vec![],
             on_move_done,
-            last_move_arrow: None,
+            on_game_over: Box::new(|_| {}),
+            on_computer_to_move: Box::new(|_| {}),
+            player_mode: PlayerMode::Both,
+            game_state,
+            arrows: vec![],
+            highlights: vec![],
+            move_entry: MoveEntry::default(),
+            animation: None,
+            animation_duration: DEFAULT_ANIMATION_DURATION,
+            pieces_display: PiecesDisplay::Visible,
+            flash_start: Instant::now(),
             colors: Colors::default(),
         }
     }
 
-    #[allow(dead_code)]
+    /// Sets the callback called once, with the result, when a move ends the game.
+    pub fn set_on_game_over(&mut self, on_game_over: Box<dyn Fn(&Outcome)>) {
+        self.on_game_over = on_game_over;
+    }
+
+    /// Sets the callback called with the position each time the computer has to play in it.
+    pub fn set_on_computer_to_move(&mut self, on_computer_to_move: Box<dyn Fn(&Board)>) {
+        self.on_computer_to_move = on_computer_to_move;
+    }
+
+    /// Sets the sides the user may move, and turns the board towards the user's side.
+    pub fn set_player_mode(&mut self, player_mode: PlayerMode) {
+        self.player_mode = player_mode;
+        match player_mode {
+            PlayerMode::WhiteHuman => self.reversed = false,
+            PlayerMode::BlackHuman => self.reversed = true,
+            PlayerMode::Both | PlayerMode::Observer => {}
+        }
+        self.dnd_data = None;
+        self.selected_cell = None;
+        self.premoves.clear();
+        self.notify_computer_to_move();
+    }
+
+    /// Whether the game goes on with the computer to move.
+    pub fn is_computer_to_move(&self) -> bool {
+        !self.game_state.is_over() && !self.player_mode.is_human(self.position.side())
+    }
+
+    fn notify_computer_to_move(&self) {
+        if self.is_computer_to_move() {
+            (self.on_computer_to_move)(&self.position);
+        }
+    }
+
+    /// Whether the user may move pieces now: it is their turn, in the current position.
+    fn accepts_user_moves(&self) -> bool {
+        !self.game_state.is_over()
+            && !self.history.is_viewing_past()
+            && self.player_mode.is_human(self.position.side())
+    }
+
+    /// Whether the user may queue premoves now: the computer is thinking about its move.
+    fn accepts_premoves(&self) -> bool {
+        !self.game_state.is_over()
+            && !self.history.is_viewing_past()
+            && !self.player_mode.is_human(self.position.side())
+            && self.player_mode.is_human(self.position.side().inv())
+    }
+
+    /// Content of a cell once the queued premoves are played, to chain them.
+    fn premoved_square(&self, (file, rank): (u8, u8)) -> Cell {
+        let mut cell = (file, rank);
+        for premove in self.premoves.iter().rev() {
+            if premove.from == cell {
+                return Cell::EMPTY;
+            }
+            if premove.to == cell {
+                cell = premove.from;
+            }
+        }
+        self.position.get2(
+            File::from_index(cell.0 as usize),
+            Rank::from_index(7 - cell.1 as usize),
+        )
+    }
+
+    /// Queues the move of `piece` between two cells, to be played in turn.
+    ///
+    /// A pawn premoved to the last rank is always promoted to a queen, as the promotion
+    /// buttons cannot be shown before the move is known to be legal.
+    fn queue_premove(&mut self, from: (u8, u8), to: (u8, u8), piece: Piece, color: Color) {
+        if from == to {
+            return;
+        }
+        let last_rank = if color == Color::White { 7 } else { 0 };
+        let promotion = (piece == Piece::Pawn && to.1 == last_rank).then_some('q');
+        self.premoves.push(Premove {
+            from,
+            to,
+            promotion,
+        });
+    }
+
+    /// Plays the first queued premove, now that the user is to move.
+    ///
+    /// The whole queue is dropped if that move turns out to be illegal.
+    fn play_premove(&mut self) {
+        if self.premoves.is_empty() || !self.accepts_user_moves() {
+            return;
+        }
+        let premove = self.premoves.remove(0);
+        let uci_move = get_uci_move_for(
+            premove.from.0,
+            premove.from.1,
+            premove.to.0,
+            premove.to.1,
+            premove.promotion,
+        );
+        let played = match uci_move.into_move(&self.position) {
+            Ok(matching_move) => self.commit_move(matching_move),
+            Err(_) => false,
+        };
+        if !played {
+            self.premoves.clear();
+        }
+    }
+
+    /// Drops the animation once it is over, then plays the premove waiting for it.
+    ///
+    /// Returns whether the animation is still running.
+    fn update_animation(&mut self) -> bool {
+        if let Some(animation) = &self.animation {
+            if animation.progress(self.animation_duration) < 1.0 {
+                return true;
+            }
+            self.animation = None;
+            self.play_premove();
+        }
+        self.animation.is_some()
+    }
+
+    /// Cancels the queued premoves. Returns whether there were some.
+    fn cancel_premoves(&mut self) -> bool {
+        let had_premoves = !self.premoves.is_empty();
+        self.premoves.clear();
+        had_premoves
+    }
+
     pub fn set_colors(&mut self, colors: Colors) {
         self.colors = colors;
     }
@@ -123,6 +481,269 @@ impl ChessBoard {
         move |ui: &mut egui::Ui| self.view(ui)
     }
 
+    /// List of the played moves. Clicking one shows the position it led to, read-only.
+    pub fn history_widget(&mut self) -> impl egui::Widget + '_ {
+        move |ui: &mut egui::Ui| self.history.show(ui)
+    }
+
+    /// Sets how long the pieces take to slide after a move. A zero duration disables animations.
+    pub fn set_animation_duration(&mut self, duration: Duration) {
+        self.animation_duration = duration;
+    }
+
+    pub fn animation_duration(&self) -> Duration {
+        self.animation_duration
+    }
+
+    /// Draws an arrow over the board, such as a hint, until the arrows are cleared or a new
+    /// position is set.
+    pub fn add_arrow(&mut self, arrow: Arrow) {
+        self.arrows.push(arrow);
+    }
+
+    pub fn clear_arrows(&mut self) {
+        self.arrows.clear();
+    }
+
+    /// Fills a cell with a colour, under the pieces, until the highlights are cleared or a new
+    /// position is set.
+    pub fn add_highlight(&mut self, highlight: SquareHighlight) {
+        self.highlights.push(highlight);
+    }
+
+    pub fn clear_highlights(&mut self) {
+        self.highlights.clear();
+    }
+
+    /// Removes the arrows and circles drawn on the displayed position.
+    pub fn clear_annotations(&mut self) {
+        self.history.annotations_mut().clear();
+    }
+
+    /// Arrows and circles drawn by the user, as PGN comment commands such as "[%cal Ge2e4]".
+    ///
+    /// Gives the commands by number of moves played in the annotated position, the starting
+    /// position being 0, for positions with annotations only.
+    pub fn annotation_comments(&self) -> Vec<(usize, String)> {
+        self.history
+            .all_annotations()
+            .enumerate()
+            .filter_map(|(ply, annotations)| Some((ply, annotations.pgn_commands()?)))
+            .collect()
+    }
+
+    /// Changes how the pieces are shown. The `Flash` display starts showing them again.
+    pub fn set_pieces_display(&mut self, pieces_display: PiecesDisplay) {
+        self.pieces_display = pieces_display;
+        self.flash_start = Instant::now();
+    }
+
+    pub fn pieces_display(&self) -> PiecesDisplay {
+        self.pieces_display
+    }
+
+    /// Shows the pieces again in the `Flash` display, for its whole duration.
+    pub fn flash_pieces(&mut self) {
+        self.flash_start = Instant::now();
+    }
+
+    /// How the pieces of `color` are drawn with the current pieces display.
+    fn piece_rendering(&self, color: Color) -> PieceRendering {
+        match self.pieces_display {
+            PiecesDisplay::Visible => PieceRendering::Image,
+            PiecesDisplay::Hidden => PieceRendering::Hidden,
+            PiecesDisplay::HideOpponent(player) if player == color => PieceRendering::Image,
+            PiecesDisplay::HideOpponent(_) => PieceRendering::Hidden,
+            PiecesDisplay::Discs => PieceRendering::Disc,
+            PiecesDisplay::Flash(duration) if self.flash_start.elapsed() < duration => {
+                PieceRendering::Image
+            }
+            PiecesDisplay::Flash(_) => PieceRendering::Hidden,
+        }
+    }
+
+    /// Whether the piece on `cell` of the displayed position is drawn, even as a disc.
+    fn shows_piece_on(&self, cell: Coord) -> bool {
+        match self.displayed_position().get(cell).color() {
+            Some(color) => self.piece_rendering(color) != PieceRendering::Hidden,
+            None => false,
+        }
+    }
+
+    /// Whether some pieces are hidden, which the hints must not give away.
+    fn hides_pieces(&self) -> bool {
+        [Color::White, Color::Black]
+            .into_iter()
+            .any(|color| self.piece_rendering(color) == PieceRendering::Hidden)
+    }
+
+    /// Text field to play moves typed in SAN or UCI, showing why the last one was refused.
+    pub fn move_entry_widget(&mut self) -> impl egui::Widget + '_ {
+        move |ui: &mut egui::Ui| {
+            let (response, submitted) = self.move_entry.show(ui);
+            if let Some(text) = submitted {
+                let result = self.play_typed_move(&text);
+                self.move_entry.set_result(result);
+            }
+            response
+        }
+    }
+
+    /// Plays a move typed in SAN, such as "Rd7" or "O-O", or in UCI, such as "e7e8q".
+    pub fn play_typed_move(&mut self, text: &str) -> Result<(), MoveEntryError> {
+        if !self.accepts_user_moves() {
+            return Err(MoveEntryError::NotPlayable);
+        }
+        let matching_move = move_entry::parse_move(text, &self.position)?;
+        if self.play_move(matching_move) {
+            Ok(())
+        } else {
+            Err(MoveEntryError::Illegal(text.trim().to_string()))
+        }
+    }
+
+    pub fn position(&self) -> &Board {
+        &self.position
+    }
+
+    pub fn set_position(&mut self, position: Board) {
+        self.game_state.reset(&position);
+        self.history = MoveHistory::new(position.clone());
+        self.position = position;
+        self.dnd_data = None;
+        self.selected_cell = None;
+        self.keyboard_cursor = None;
+        self.annotation_start = None;
+        self.animation = None;
+        self.arrows.clear();
+        self.highlights.clear();
+        self.premoves.clear();
+        self.flash_start = Instant::now();
+        self.notify_computer_to_move();
+    }
+
+    /// Starts a new game from a FEN string. The board is left unchanged if the FEN is invalid.
+    pub fn set_position_fen(&mut self, fen: &str) -> Result<(), PositionError> {
+        let position = Board::from_fen(fen.trim())?;
+        self.set_position(position);
+        Ok(())
+    }
+
+    pub fn position_fen(&self) -> String {
+        self.position.as_fen()
+    }
+
+    /// Position the game started from.
+    pub fn start_position(&self) -> &Board {
+        self.history.start()
+    }
+
+    /// Moves played since the starting position, taken back ones excepted.
+    pub fn moves(&self) -> Vec<Move> {
+        self.history.moves().collect()
+    }
+
+    /// Result of the game, once it is over. No more moves can be played then.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.game_state.outcome()
+    }
+
+    /// Animation of the last move, unless a past position is viewed.
+    fn displayed_animation(&self) -> Option<&MoveAnimation> {
+        if self.history.is_viewing_past() {
+            return None;
+        }
+        self.animation.as_ref()
+    }
+
+    /// Position drawn on the board: the live one, or the one picked in the history.
+    fn displayed_position(&self) -> &Board {
+        self.history.viewed_position().unwrap_or(&self.position)
+    }
+
+    fn displayed_last_move_arrow(&self) -> Option<Arrow> {
+        let last_move = match self.history.viewed_move() {
+            Some(viewed_move) => viewed_move,
+            None => self.history.last_move(),
+        };
+        last_move.map(|mv| Arrow::for_move(mv, self.colors.last_move_arrow))
+    }
+
+    /// Cells the selected or dragged piece can go to, each one given once.
+    fn legal_destinations(&self) -> Vec<Coord> {
+        let (file, rank) = match (&self.dnd_data, self.selected_cell) {
+            (Some(dnd_data), _) if !dnd_data.has_pending_promotion => {
+                (dnd_data.start_file, dnd_data.start_rank)
+            }
+            (None, Some(selected_cell)) => selected_cell,
+            _ => return vec![],
+        };
+        let src = Coord::from_parts(
+            File::from_index(file as usize),
+            Rank::from_index(7 - rank as usize),
+        );
+        let mut destinations: Vec<Coord> = legal::gen_all(&self.position)
+            .iter()
+            .filter(|mv| mv.src() == src)
+            .map(|mv| mv.dst())
+            .collect();
+        // Promotions give one move per piece for the same cell.
+        destinations.sort_by_key(|dst| dst.index());
+        destinations.dedup();
+        destinations
+    }
+
+    /// Takes back the last move. Returns false if there is no move to take back.
+    pub fn undo(&mut self) -> bool {
+        let position = match self.history.undo() {
+            Some(position) => position.clone(),
+            None => return false,
+        };
+        self.restore(position);
+        true
+    }
+
+    /// Plays again the last move taken back. Returns false if there is none.
+    pub fn redo(&mut self) -> bool {
+        let position = match self.history.redo() {
+            Some(position) => position.clone(),
+            None => return false,
+        };
+        // The game may be over again, but `on_game_over` was already called when it ended.
+        self.restore(position);
+        true
+    }
+
+    /// Takes back the last move of each side, so that the same side is to move again.
+    ///
+    /// Returns false if there is no move to take back.
+    pub fn take_back_pair(&mut self) -> bool {
+        if !self.undo() {
+            return false;
+        }
+        self.undo();
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Goes to a position of the history, keeping the arrow and the game state in line with it.
+    fn restore(&mut self, position: Board) {
+        self.position = position;
+        self.dnd_data = None;
+        self.selected_cell = None;
+        self.animation = None;
+        self.premoves.clear();
+        self.game_state.replay(self.history.positions());
+        self.notify_computer_to_move();
+    }
+
     pub fn toggle_orientation(&mut self) {
         self.reversed = !self.reversed;
     }
@@ -132,39 +753,103 @@ impl ChessBoard {
         let desired_size = egui::vec2(self.size, self.size);
 
         // 2. Allocating space:
-        let (rect, response) = ui.allocate_exact_size(desired_size, egui::Sense::drag());
+        let (rect, mut response) =
+            ui.allocate_exact_size(desired_size, egui::Sense::click_and_drag());
 
         // 3. Interact: Time to check for clicks!
-        if response.drag_started() {
+        // egui turns Enter and Space into clicks for the focused widget.
+        let activated_by_keyboard = response.has_focus() && {
+            let input = ui.input();
+            input.key_pressed(Key::Enter) || input.key_pressed(Key::Space)
+        };
+        let secondary_down = ui.input().pointer.button_down(PointerButton::Secondary);
+        if activated_by_keyboard {
+            // Handled with the other keys.
+        } else if response.drag_started() && secondary_down {
+            // A right click cancels the premoves, rather than starting an annotation.
+            if !self.cancel_premoves() {
+                let location = response.ctx.pointer_interact_pos().unwrap();
+                self.annotation_start = self.cell_at(location - Pos2::ZERO, rect);
+            }
+        } else if response.drag_released() && self.annotation_start.is_some() {
+            let location = response.ctx.pointer_interact_pos().unwrap();
+            let modifiers = ui.input().modifiers;
+            self.handle_annotation_released(location - Pos2::ZERO, rect, modifiers);
+        } else if self.annotation_start.is_some() {
+            // The arrow is drawn once the right button is released.
+        } else if response.clicked() {
+            response.request_focus();
+            let location = response.ctx.pointer_interact_pos().unwrap();
+            let location = location - Pos2::ZERO;
+            self.handle_click(location, rect);
+        } else if response.drag_started() {
             let location = response.ctx.pointer_interact_pos().unwrap();
             let location = location - Pos2::ZERO;
             self.handle_drag_started(location, rect);
         } else if response.drag_released() {
             let location = response.ctx.pointer_interact_pos().unwrap();
             let location = location - Pos2::ZERO;
+            self.selected_cell = None;
             self.handle_drag_released(location, rect);
         } else if response.dragged() {
             let location = response.ctx.pointer_interact_pos().unwrap();
             let location = location - Pos2::ZERO;
             self.handle_drag(location, rect);
         }
+        // egui drops the focus on Escape, which only cancels the selection here.
+        let escaped = response.lost_focus() && ui.input().key_pressed(Key::Escape);
+        if escaped {
+            response.request_focus();
+        }
+        let focused = response.has_focus() || escaped;
+        if focused && self.handle_keys(ui, rect, activated_by_keyboard) {
+            response.mark_changed();
+        }
+        response.widget_info(|| {
+            WidgetInfo::labeled(WidgetType::Other, self.describe_keyboard_cursor())
+        });
+
+        if self.update_animation() {
+            ui.ctx().request_repaint();
+        }
+        // The pieces must disappear once the flash is over, even without any input.
+        if let PiecesDisplay::Flash(duration) = self.pieces_display {
+            if let Some(remaining) = duration.checked_sub(self.flash_start.elapsed()) {
+                ui.ctx().request_repaint_after(remaining);
+            }
+        }
 
         // 4. Paint!
         // Make sure we need to paint:
         if ui.is_rect_visible(rect) {
             painter::draw_background(ui, rect, &self.colors);
-            painter::draw_cells(ui, rect, &self);
-            painter::draw_last_move_arrow(ui, rect, &self);
-            painter::draw_pieces(ui, rect, &self);
+            painter::draw_cells(ui, rect, self);
+            painter::draw_highlights(ui, rect, self);
+            painter::draw_premoves(ui, rect, self);
+            painter::draw_king_in_check(ui, rect, self);
+            painter::draw_last_move_arrow(ui, rect, self);
+            painter::draw_arrows(ui, rect, self);
+            painter::draw_pieces(ui, rect, self);
+            painter::draw_animation(ui, rect, self);
+            painter::draw_legal_destinations(ui, rect, self);
+            painter::draw_annotations(ui, rect, self);
+            if response.has_focus() {
+                painter::draw_keyboard_cursor(ui, rect, self);
+            }
+            painter::draw_game_end_marker(ui, rect, self);
             painter::draw_coordinates(ui, rect, self);
-            painter::draw_player_turn(ui, rect, &self);
-            painter::draw_moved_piece(ui, rect, &self);
+            painter::draw_player_turn(ui, rect, self);
+            painter::draw_moved_piece(ui, rect, self);
             painter::draw_promotion_buttons(ui, rect, self);
         }
         response
     }
 
     fn handle_drag_started(&mut self, location: Vec2, rect: Rect) {
+        let premove = self.accepts_premoves();
+        if !self.accepts_user_moves() && !premove {
+            return;
+        }
         if let Some(dnd_data) = &self.dnd_data {
             if dnd_data.has_pending_promotion {
                 return;
@@ -180,7 +865,7 @@ impl ChessBoard {
         let col = ((x - cells_size * 0.5) / cells_size).floor() as i32;
         let row = ((y - cells_size * 0.5) / cells_size).floor() as i32;
 
-        if col < 0 || col > 7 || row < 0 || row > 7 {
+        if !(0..=7).contains(&col) || !(0..=7).contains(&row) {
             return;
         }
 
@@ -190,10 +875,14 @@ impl ChessBoard {
         let file = if self.reversed { 7 - col } else { col };
         let rank = if self.reversed { row } else { 7 - row };
 
-        let square = self.position.get2(
-            File::from_index(file as usize),
-            Rank::from_index(7 - rank as usize),
-        );
+        let square = if premove {
+            self.premoved_square((file, rank))
+        } else {
+            self.position.get2(
+                File::from_index(file as usize),
+                Rank::from_index(7 - rank as usize),
+            )
+        };
         if square.is_free() {
             return;
         }
@@ -201,7 +890,11 @@ impl ChessBoard {
         let piece_type = square.piece().unwrap();
         let piece_color = square.color().unwrap();
 
-        let player_turn = self.position.side();
+        let player_turn = if premove {
+            self.position.side().inv()
+        } else {
+            self.position.side()
+        };
         let is_not_our_turn = player_turn != piece_color;
 
         if is_not_our_turn {
@@ -219,6 +912,7 @@ impl ChessBoard {
             end_rank: rank,
             has_pending_promotion: false,
             started_with_reversed_board: self.reversed,
+            premove,
         });
     }
 
@@ -241,7 +935,7 @@ impl ChessBoard {
         let col = ((x - cells_size * 0.5) / cells_size).floor() as i32;
         let row = ((y - cells_size * 0.5) / cells_size).floor() as i32;
 
-        if col < 0 || col > 7 || row < 0 || row > 7 {
+        if !(0..=7).contains(&col) || !(0..=7).contains(&row) {
             self.dnd_data = None;
             return;
         }
@@ -254,6 +948,14 @@ impl ChessBoard {
 
         let dnd_data = self.dnd_data.as_mut().unwrap();
 
+        if dnd_data.premove {
+            let start = (dnd_data.start_file, dnd_data.start_rank);
+            let (piece_type, piece_color) = (dnd_data.piece_type, dnd_data.piece_color);
+            self.queue_premove(start, (file, rank), piece_type, piece_color);
+            self.dnd_data = None;
+            return;
+        }
+
         let start_square = self.position.get2(
             File::from_index(dnd_data.start_file as usize),
             Rank::from_index(7 - dnd_data.start_rank as usize),
@@ -281,71 +983,320 @@ impl ChessBoard {
             let uci_move = get_uci_move_for(
                 dnd_data.start_file,
                 dnd_data.start_rank,
-                file as u8,
-                rank as u8,
+                file,
+                rank,
                 None,
             );
-            let matching_move = uci_move.into_move(&self.position);
-
-            let move_san = match matching_move {
-                Ok(matching_move) => match matching_move.san(&self.position) {
-                    Ok(san) => Some(san.to_string()),
-                    _ => None,
-                },
-                Err(_) => None,
-            };
+            if let Ok(matching_move) = uci_move.into_move(&self.position) {
+                self.commit_move(matching_move);
+                // The piece has been dropped on its target cell already.
+                self.animation = None;
+            }
+        }
 
-            if let Ok(matching_move) = matching_move {
-                match matching_move.make_raw(&mut self.position) {
-                    Ok(_) => {
-                        // move has been validated
-                        self.last_move_arrow = Some((
-                            matching_move.src().file().index() as u8,
-                            matching_move.src().rank().index() as u8,
-                            matching_move.dst().file().index() as u8,
-                            matching_move.dst().rank().index() as u8,
-                        ));
-                        let white_turn_before_move = self.position.side() == Color::Black;
-                        (self.on_move_done)(&utils::san_to_fan(
-                            move_san.unwrap(),
-                            white_turn_before_move,
-                        ));
-                    }
-                    _ => {}
+        self.dnd_data = None;
+    }
+
+    /// Selects a piece of the side to move, or plays the selected piece to the clicked cell.
+    ///
+    /// Clicking the selected piece again, or a cell it cannot go to, cancels the selection.
+    fn handle_click(&mut self, location: Vec2, rect: Rect) {
+        match self.cell_at(location, rect) {
+            Some((file, rank)) => {
+                self.keyboard_cursor = Some((file, rank));
+                self.click_cell(file, rank, rect);
+            }
+            None => {
+                if !self.has_pending_promotion() {
+                    self.dnd_data = None;
+                    self.selected_cell = None;
                 }
             }
         }
+    }
 
+    /// Clicks on a cell, with the mouse or the keyboard: see `handle_click`.
+    fn click_cell(&mut self, file: u8, rank: u8, rect: Rect) {
+        let premove = self.accepts_premoves();
+        if !self.accepts_user_moves() && !premove {
+            return;
+        }
+        if self.has_pending_promotion() {
+            return;
+        }
+        // The press of the click started a drag and drop, which is now over.
         self.dnd_data = None;
+
+        let (clicked_square, user_side) = if premove {
+            (
+                self.premoved_square((file, rank)),
+                self.position.side().inv(),
+            )
+        } else {
+            let square = self.position.get2(
+                File::from_index(file as usize),
+                Rank::from_index(7 - rank as usize),
+            );
+            (square, self.position.side())
+        };
+        let own_piece = clicked_square.color() == Some(user_side);
+
+        let (start_file, start_rank) = match self.selected_cell {
+            Some(selected_cell) if selected_cell == (file, rank) => {
+                self.selected_cell = None;
+                return;
+            }
+            Some(selected_cell) if !own_piece => selected_cell,
+            _ => {
+                self.selected_cell = own_piece.then_some((file, rank));
+                return;
+            }
+        };
+        self.selected_cell = None;
+
+        let start_square = if premove {
+            self.premoved_square((start_file, start_rank))
+        } else {
+            self.position.get2(
+                File::from_index(start_file as usize),
+                Rank::from_index(7 - start_rank as usize),
+            )
+        };
+        let (piece_type, piece_color) = match (start_square.piece(), start_square.color()) {
+            (Some(piece_type), Some(piece_color)) => (piece_type, piece_color),
+            _ => return,
+        };
+        if premove {
+            self.queue_premove(
+                (start_file, start_rank),
+                (file, rank),
+                piece_type,
+                piece_color,
+            );
+            return;
+        }
+        let is_promotion = piece_type == Piece::Pawn
+            && ((piece_color == Color::White && rank == 7)
+                || (piece_color == Color::Black && rank == 0));
+
+        if is_promotion {
+            let uci_move = get_uci_move_for(start_file, start_rank, file, rank, Some('q'));
+            if uci_move.into_move(&self.position).is_err() {
+                return;
+            }
+            // Same flow as a dropped pawn: the piece waits on the target cell for the choice.
+            let cells_size = rect.size().x * 0.111;
+            let col = if self.reversed { 7 - file } else { file };
+            let row = if self.reversed { rank } else { 7 - rank };
+            self.dnd_data = Some(DndData {
+                piece_type,
+                piece_color,
+                x: rect.min.x + cells_size * (1.0 + col as f32),
+                y: rect.min.y + cells_size * (1.0 + row as f32),
+                start_file,
+                start_rank,
+                end_file: file,
+                end_rank: rank,
+                has_pending_promotion: true,
+                started_with_reversed_board: self.reversed,
+                premove: false,
+            });
+            return;
+        }
+
+        let uci_move = get_uci_move_for(start_file, start_rank, file, rank, None);
+        if let Ok(matching_move) = uci_move.into_move(&self.position) {
+            self.commit_move(matching_move);
+        }
     }
 
-    fn handle_drag(&mut self, location: Vec2, rect: Rect) {
-        match &mut self.dnd_data {
-            Some(dnd_data) => {
-                if dnd_data.has_pending_promotion {
-                    return;
+    /// Circles the cell where the right button was pressed, or draws an arrow from it.
+    fn handle_annotation_released(&mut self, location: Vec2, rect: Rect, modifiers: Modifiers) {
+        let start = match self.annotation_start.take() {
+            Some(start) => start,
+            None => return,
+        };
+        let end = match self.cell_at(location, rect) {
+            Some(end) => end,
+            None => return,
+        };
+        let coord = |(file, rank): (u8, u8)| {
+            Coord::from_parts(
+                File::from_index(file as usize),
+                Rank::from_index(7 - rank as usize),
+            )
+        };
+        let color = AnnotationColor::from_modifiers(modifiers);
+        let annotations = self.history.annotations_mut();
+        if start == end {
+            annotations.toggle_circle(color, coord(start));
+        } else {
+            annotations.toggle_arrow(color, coord(start), coord(end));
+        }
+    }
+
+    fn has_pending_promotion(&self) -> bool {
+        matches!(&self.dnd_data, Some(dnd_data) if dnd_data.has_pending_promotion)
+    }
+
+    /// Moves the keyboard cursor, selects and drops pieces, and chooses the promotion piece.
+    ///
+    /// Returns whether something changed, to be announced by screen readers.
+    fn handle_keys(&mut self, ui: &Ui, rect: Rect, activated: bool) -> bool {
+        let (left, right, up, down, escape) = {
+            let input = ui.input();
+            (
+                input.key_pressed(Key::ArrowLeft),
+                input.key_pressed(Key::ArrowRight),
+                input.key_pressed(Key::ArrowUp),
+                input.key_pressed(Key::ArrowDown),
+                input.key_pressed(Key::Escape),
+            )
+        };
+
+        if escape {
+            let changed = self.dnd_data.is_some() || self.selected_cell.is_some();
+            self.dnd_data = None;
+            self.selected_cell = None;
+            return self.cancel_premoves() || changed;
+        }
+
+        if self.has_pending_promotion() {
+            let input = ui.input();
+            let promotion = [(Key::Q, 'q'), (Key::R, 'r'), (Key::B, 'b'), (Key::N, 'n')]
+                .into_iter()
+                .find(|(key, _)| input.key_pressed(*key))
+                .map(|(_, piece)| piece);
+            drop(input);
+            return match promotion {
+                Some(piece) => {
+                    self.commit_promotion(piece);
+                    true
+                }
+                None => false,
+            };
+        }
+
+        let (mut file, mut rank) = self.keyboard_cursor();
+        // Up goes towards the top of the screen, whatever the orientation of the board.
+        let (forward, rightward) = if self.reversed { (-1, -1) } else { (1, 1) };
+        let moved = |value: u8, delta: i32| (value as i32 + delta).clamp(0, 7) as u8;
+        if left {
+            file = moved(file, -rightward);
+        }
+        if right {
+            file = moved(file, rightward);
+        }
+        if up {
+            rank = moved(rank, forward);
+        }
+        if down {
+            rank = moved(rank, -forward);
+        }
+        let cursor_moved = self.keyboard_cursor != Some((file, rank));
+        self.keyboard_cursor = Some((file, rank));
+
+        if activated {
+            self.click_cell(file, rank, rect);
+        }
+        cursor_moved || activated
+    }
+
+    /// Cell of the keyboard cursor as (file, rank), starting on the king of the side to move.
+    fn keyboard_cursor(&self) -> (u8, u8) {
+        self.keyboard_cursor.unwrap_or_else(|| {
+            let king = self.position.king_pos(self.position.side());
+            (king.file().index() as u8, 7 - king.rank().index() as u8)
+        })
+    }
+
+    /// What screen readers announce for the keyboard cursor, such as "white rook on d7".
+    fn describe_keyboard_cursor(&self) -> String {
+        if self.has_pending_promotion() {
+            return String::from("choose the promotion piece : Q, R, B or N");
+        }
+        let describe = |(file, rank): (u8, u8)| {
+            let name = format!("{}{}", (b'a' + file) as char, rank + 1);
+            let cell = Coord::from_parts(
+                File::from_index(file as usize),
+                Rank::from_index(7 - rank as usize),
+            );
+            let square = self.displayed_position().get(cell);
+            // The blindfold exercises must not be given away by the description.
+            if square.is_occupied() && !self.shows_piece_on(cell) {
+                return format!("{}, hidden", name);
+            }
+            match (square.color(), square.piece()) {
+                (Some(color), Some(_)) if self.piece_rendering(color) == PieceRendering::Disc => {
+                    format!("{} piece on {}", color.as_long_str(), name)
                 }
-                let size = rect.size().x;
-                let cells_size = size * 0.111;
+                (Some(color), Some(piece)) => format!(
+                    "{} {} on {}",
+                    color.as_long_str(),
+                    piece_name(piece),
+                    name
+                ),
+                _ => format!("empty {}", name),
+            }
+        };
+        let cursor = self.keyboard_cursor();
+        match self.selected_cell {
+            Some(selected_cell) if selected_cell == cursor => {
+                format!("{}, selected", describe(cursor))
+            }
+            Some(selected_cell) => {
+                format!("{}, {} selected", describe(cursor), describe(selected_cell))
+            }
+            None => describe(cursor),
+        }
+    }
 
-                let x = location.x - rect.min.x;
-                let y = location.y - rect.min.y;
+    /// Cell under `location` as (file, rank), or `None` outside of the cells.
+    fn cell_at(&self, location: Vec2, rect: Rect) -> Option<(u8, u8)> {
+        let cells_size = rect.size().x * 0.111;
 
-                let col = ((x - cells_size * 0.5) / cells_size).floor() as i32;
-                let row = ((y - cells_size * 0.5) / cells_size).floor() as i32;
+        let x = location.x - rect.min.x;
+        let y = location.y - rect.min.y;
+
+        let col = ((x - cells_size * 0.5) / cells_size).floor() as i32;
+        let row = ((y - cells_size * 0.5) / cells_size).floor() as i32;
 
-                let col = col as u8;
-                let row = row as u8;
+        if !(0..=7).contains(&col) || !(0..=7).contains(&row) {
+            return None;
+        }
 
-                let file = if self.reversed { 7 - col } else { col };
-                let rank = if self.reversed { 7 - row } else { row };
+        let col = col as u8;
+        let row = row as u8;
+
+        let file = if self.reversed { 7 - col } else { col };
+        let rank = if self.reversed { row } else { 7 - row };
+        Some((file, rank))
+    }
 
-                dnd_data.x = location.x;
-                dnd_data.y = location.y;
-                dnd_data.end_file = file;
-                dnd_data.end_rank = rank;
+    fn handle_drag(&mut self, location: Vec2, rect: Rect) {
+        if let Some(dnd_data) = &mut self.dnd_data {
+            if dnd_data.has_pending_promotion {
+                return;
             }
-            _ => {}
+            let size = rect.size().x;
+            let cells_size = size * 0.111;
+
+            let x = location.x - rect.min.x;
+            let y = location.y - rect.min.y;
+
+            let col = ((x - cells_size * 0.5) / cells_size).floor() as i32;
+            let row = ((y - cells_size * 0.5) / cells_size).floor() as i32;
+
+            let col = col as u8;
+            let row = row as u8;
+
+            let file = if self.reversed { 7 - col } else { col };
+            let rank = if self.reversed { 7 - row } else { row };
+
+            dnd_data.x = location.x;
+            dnd_data.y = location.y;
+            dnd_data.end_file = file;
+            dnd_data.end_rank = rank;
         }
     }
 
@@ -370,36 +1321,272 @@ impl ChessBoard {
             Some(promotion_type.to_ascii_lowercase()),
         );
 
-        let matching_move = uci_move.into_move(&self.position);
+        if let Ok(matching_move) = uci_move.into_move(&self.position) {
+            self.commit_move(matching_move);
+            // The pawn waited on its target cell for the choice.
+            self.animation = None;
+        }
+
+        self.dnd_data = None;
+    }
 
-        let move_san = match matching_move {
-            Ok(matching_move) => match matching_move.san(&self.position) {
-                Ok(san) => Some(san.to_string()),
-                _ => None,
-            },
-            Err(_) => None,
+    /// Plays a move coming from outside of the board, such as the computer's reply, then the
+    /// first premove of the user once the reply has been animated.
+    ///
+    /// Any drag and drop in progress is cancelled. Returns false if the move is illegal.
+    pub fn play_move(&mut self, matching_move: Move) -> bool {
+        self.dnd_data = None;
+        self.selected_cell = None;
+        if !self.commit_move(matching_move) {
+            return false;
+        }
+        if self.animation.is_none() {
+            self.play_premove();
+        } else {
+            self.update_animation();
+        }
+        if self.game_state.is_over() {
+            self.premoves.clear();
+        }
+        true
+    }
+
+    fn commit_move(&mut self, matching_move: Move) -> bool {
+        if self.game_state.is_over() {
+            return false;
+        }
+        let move_san = match matching_move.san(&self.position) {
+            Ok(san) => san.to_string(),
+            Err(_) => return false,
         };
 
-        if let Ok(matching_move) = matching_move {
-            match matching_move.make_raw(&mut self.position) {
-                Ok(_) => {
-                    // move has been validated
-                    self.last_move_arrow = Some((
-                        matching_move.src().file().index() as u8,
-                        matching_move.src().rank().index() as u8,
-                        matching_move.dst().file().index() as u8,
-                        matching_move.dst().rank().index() as u8,
-                    ));
-                    let white_turn_before_move = self.position.side() == Color::Black;
-                    (self.on_move_done)(&utils::san_to_fan(
-                        move_san.unwrap(),
-                        white_turn_before_move,
-                    ));
-                }
-                _ => {}
-            }
+        let animation = MoveAnimation::new(&self.position, matching_move);
+        let before = self.position.clone();
+        if matching_move.make_raw(&mut self.position).is_err() {
+            return false;
+        }
+        self.animation = animation;
+
+        // move has been validated
+        self.history.push(PlayedMove {
+            mv: matching_move,
+            san: move_san.clone(),
+            position: self.position.clone(),
+            annotations: Annotations::default(),
+        });
+        let event = MoveEvent::new(
+            matching_move,
+            move_san,
+            &before,
+            &self.position,
+            self.history.ply(),
+        );
+        (self.on_move_done)(&event);
+        if let Some(outcome) = self.game_state.record(&self.position) {
+            (self.on_game_over)(&outcome);
         }
+        self.notify_computer_to_move();
+        true
+    }
+}
 
-        self.dnd_data = None;
+fn piece_name(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => "pawn",
+        Piece::Knight => "knight",
+        Piece::Bishop => "bishop",
+        Piece::Rook => "rook",
+        Piece::Queen => "queen",
+        Piece::King => "king",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use owlchess::WinReason;
+    use std::{cell::RefCell, rc::Rc};
+
+    fn board(fen: &str, player_mode: PlayerMode) -> ChessBoard {
+        let mut board = ChessBoard::new(400.0, Box::new(|_| {}));
+        board.set_position_fen(fen).unwrap();
+        board.set_player_mode(player_mode);
+        board
+    }
+
+    fn play(board: &mut ChessBoard, uci: &str) {
+        let mv = Move::from_uci_legal(uci, board.position()).unwrap();
+        assert!(board.play_move(mv));
+    }
+
+    #[test]
+    fn invalid_fen_leaves_the_board_unchanged() {
+        let mut board = board(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+            PlayerMode::Both,
+        );
+        play(&mut board, "e7e5");
+        let fen = board.position_fen();
+
+        for invalid in ["", "not a position", "8/8/8/8/8/8/8/8 w - - 0 1"] {
+            assert!(matches!(
+                board.set_position_fen(invalid),
+                Err(PositionError::InvalidFen(_))
+            ));
+        }
+        assert_eq!(board.position_fen(), fen);
+        assert_eq!(board.moves().len(), 1);
+    }
+
+    #[test]
+    fn new_position_resets_the_game() {
+        let mut board = board(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+            PlayerMode::Both,
+        );
+        play(&mut board, "e7e5");
+        play(&mut board, "g1f3");
+        board.undo();
+        board.keyboard_cursor = Some((4, 4));
+        board.annotation_start = Some((3, 3));
+
+        let fen = "8/8/8/4k3/8/8/8/4K2Q w - - 0 1";
+        board.set_position_fen(fen).unwrap();
+        assert_eq!(board.position_fen(), fen);
+        assert_eq!(board.start_position().as_fen(), fen);
+        assert!(board.moves().is_empty());
+        assert!(!board.can_undo());
+        assert!(!board.can_redo());
+        assert_eq!(board.keyboard_cursor, None);
+        assert_eq!(board.annotation_start, None);
+    }
+
+    /// Events given to `on_move_done` for the moves played from `fen`.
+    fn move_events(fen: &str, moves: &[&str]) -> Vec<MoveEvent> {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&events);
+        let mut board = ChessBoard::new(
+            400.0,
+            Box::new(move |event| recorded.borrow_mut().push(event.clone())),
+        );
+        board.set_position_fen(fen).unwrap();
+        for uci in moves {
+            play(&mut board, uci);
+        }
+        let events = events.borrow().clone();
+        events
+    }
+
+    #[test]
+    fn move_events_describe_the_moves() {
+        let events = move_events("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", &["b7a8q", "e8e7"]);
+        let promotion = &events[0];
+        assert_eq!(promotion.uci, "b7a8q");
+        assert_eq!(promotion.san, "bxa8=Q+");
+        assert_eq!(promotion.side, Color::White);
+        assert_eq!(promotion.ply, 1);
+        assert!(promotion.capture);
+        assert!(promotion.check);
+        assert!(!promotion.checkmate);
+        assert_eq!(promotion.promotion, Some(Piece::Queen));
+        assert_eq!(promotion.after, events[1].before);
+
+        let reply = &events[1];
+        assert_eq!(reply.side, Color::Black);
+        assert_eq!(reply.ply, 2);
+        assert!(!reply.capture && !reply.check);
+        assert_eq!(reply.promotion, None);
+
+        let events = move_events("6k1/5ppp/8/8/8/8/8/K3R3 w - - 0 1", &["e1e8"]);
+        let mate = &events[0];
+        assert_eq!(mate.san, "Re8#");
+        assert!(!mate.capture);
+        assert!(mate.check && mate.checkmate);
+        assert_eq!(mate.promotion, None);
+    }
+
+    #[test]
+    fn game_over_is_reported_once() {
+        let mut board = board("6k1/5ppp/8/8/8/8/8/K3R3 w - - 0 1", PlayerMode::Both);
+        let outcomes = Rc::new(RefCell::new(Vec::new()));
+        let reported = Rc::clone(&outcomes);
+        board.set_on_game_over(Box::new(move |outcome| {
+            reported.borrow_mut().push(*outcome)
+        }));
+
+        play(&mut board, "e1e8");
+        assert!(board.undo());
+        assert!(board.redo());
+        assert_eq!(
+            board.outcome(),
+            Some(Outcome::Win {
+                side: Color::White,
+                reason: WinReason::Checkmate,
+            })
+        );
+        assert_eq!(*outcomes.borrow(), vec![board.outcome().unwrap()]);
+    }
+
+    #[test]
+    fn keyboard_cursor_does_not_describe_hidden_pieces() {
+        let mut board = board("4k3/3p4/8/8/8/8/8/4K3 w - - 0 1", PlayerMode::WhiteHuman);
+        board.keyboard_cursor = Some((3, 6));
+        assert_eq!(board.describe_keyboard_cursor(), "black pawn on d7");
+
+        board.set_pieces_display(PiecesDisplay::Discs);
+        assert_eq!(board.describe_keyboard_cursor(), "black piece on d7");
+
+        board.set_pieces_display(PiecesDisplay::HideOpponent(Color::White));
+        assert_eq!(board.describe_keyboard_cursor(), "d7, hidden");
+        board.keyboard_cursor = Some((4, 0));
+        assert_eq!(board.describe_keyboard_cursor(), "white king on e1");
+        board.keyboard_cursor = Some((4, 4));
+        assert_eq!(board.describe_keyboard_cursor(), "empty e5");
+    }
+
+    #[test]
+    fn premove_waits_for_the_reply_animation() {
+        let mut board = board(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+            PlayerMode::WhiteHuman,
+        );
+        board.queue_premove((3, 1), (3, 3), Piece::Pawn, Color::White);
+
+        play(&mut board, "e7e5");
+        assert_eq!(board.moves().len(), 1);
+        assert!(board.update_animation());
+        assert_eq!(board.moves().len(), 1);
+
+        board.set_animation_duration(Duration::ZERO);
+        board.update_animation();
+        assert_eq!(board.moves().len(), 2);
+        assert!(board.premoves.is_empty());
+    }
+
+    #[test]
+    fn premove_is_played_at_once_without_animations() {
+        let mut board = board(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+            PlayerMode::WhiteHuman,
+        );
+        board.set_animation_duration(Duration::ZERO);
+        board.queue_premove((3, 1), (3, 3), Piece::Pawn, Color::White);
+
+        play(&mut board, "e7e5");
+        assert_eq!(board.moves().len(), 2);
+        assert_eq!(board.position().side(), Color::Black);
+    }
+
+    #[test]
+    fn premoved_pawn_is_promoted_to_a_queen() {
+        let mut board = board("8/4P3/8/8/8/8/k7/4K3 b - - 0 1", PlayerMode::WhiteHuman);
+        board.set_animation_duration(Duration::ZERO);
+        board.queue_premove((4, 6), (4, 7), Piece::Pawn, Color::White);
+
+        play(&mut board, "a2a3");
+        assert_eq!(
+            board.position().get(Coord::from_parts(File::E, Rank::R8)),
+            Cell::from_parts(Color::White, Piece::Queen)
+        );
     }
 }
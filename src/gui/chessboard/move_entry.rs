@@ -0,0 +1,88 @@
+use std::fmt;
+
+use eframe::{
+    egui::{self, Key, TextEdit, Ui},
+    epaint::Color32,
+};
+use owlchess::{moves::san, Board, Move};
+
+#[derive(Debug)]
+pub enum MoveEntryError {
+    Unreadable(String),
+    Illegal(String),
+    Ambiguous(String),
+    /// The game is over, a past position is being viewed, or the computer is to move.
+    NotPlayable,
+}
+
+impl fmt::Display for MoveEntryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable(text) => write!(f, "not a move : {}", text),
+            Self::Illegal(text) => write!(f, "illegal move : {}", text),
+            Self::Ambiguous(text) => write!(f, "ambiguous move : {}", text),
+            Self::NotPlayable => write!(f, "no move can be played now"),
+        }
+    }
+}
+
+impl std::error::Error for MoveEntryError {}
+
+/// Reads a move typed in UCI, such as "e7e8q", or in SAN, such as "Rd7" or "O-O".
+pub(crate) fn parse_move(text: &str, position: &Board) -> Result<Move, MoveEntryError> {
+    let text = text.trim();
+    if let Ok(mv) = Move::from_uci_legal(text, position) {
+        return Ok(mv);
+    }
+    Move::from_san(text, position).map_err(|err| match err {
+        san::ParseError::Parse(_) => MoveEntryError::Unreadable(text.to_string()),
+        san::ParseError::Convert(san::IntoMoveError::Ambiguity(..)) => {
+            MoveEntryError::Ambiguous(text.to_string())
+        }
+        san::ParseError::Convert(_) => MoveEntryError::Illegal(text.to_string()),
+    })
+}
+
+/// Text field where a move can be typed, with the error of the last one tried.
+#[derive(Default)]
+pub(crate) struct MoveEntry {
+    text: String,
+    error: Option<MoveEntryError>,
+}
+
+impl MoveEntry {
+    /// Shows the field, and returns the text once the user submits it.
+    pub(crate) fn show(&mut self, ui: &mut Ui) -> (egui::Response, Option<String>) {
+        let mut submitted = None;
+        let response = ui
+            .vertical(|ui| {
+                ui.horizontal(|ui| {
+                    let text_edit = TextEdit::singleline(&mut self.text)
+                        .hint_text("Type a move : Rd7, e7e8q, O-O");
+                    let text_response = ui.add(text_edit);
+                    let entered = text_response.lost_focus() && ui.input().key_pressed(Key::Enter);
+                    if (ui.button("Play").clicked() || entered) && !self.text.trim().is_empty() {
+                        submitted = Some(self.text.clone());
+                        // Keeps typing moves without clicking the field again.
+                        text_response.request_focus();
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error.to_string());
+                }
+            })
+            .response;
+        (response, submitted)
+    }
+
+    /// Shows the result of the submitted move: the field is cleared once it has been played.
+    pub(crate) fn set_result(&mut self, result: Result<(), MoveEntryError>) {
+        match result {
+            Ok(()) => {
+                self.text.clear();
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+}
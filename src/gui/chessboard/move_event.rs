@@ -0,0 +1,48 @@
+use owlchess::{Board, Color, Move, MoveKind, Piece};
+
+use super::utils::san_to_fan;
+
+/// A move just played on the board, by the user or from outside, as given to `on_move_done`.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct MoveEvent {
+    pub mv: Move,
+    /// The move in UCI, such as "e7e8q".
+    pub uci: String,
+    pub san: String,
+    /// The move in SAN, with figurines instead of the piece letters.
+    pub fan: String,
+    pub before: Board,
+    pub after: Board,
+    pub side: Color,
+    /// Number of moves played on the board since the game started, this one included.
+    pub ply: usize,
+    pub capture: bool,
+    pub check: bool,
+    pub checkmate: bool,
+    /// Piece the pawn was promoted to, if any.
+    pub promotion: Option<Piece>,
+}
+
+impl MoveEvent {
+    /// Describes `mv`, played in `before` and giving `after`.
+    pub(crate) fn new(mv: Move, san: String, before: &Board, after: &Board, ply: usize) -> Self {
+        let side = before.side();
+        let capture = mv.kind() == MoveKind::Enpassant || before.get(mv.dst()).is_occupied();
+        let check = after.is_check();
+        Self {
+            mv,
+            uci: mv.uci().to_string(),
+            fan: san_to_fan(san.clone(), side == Color::White),
+            san,
+            before: before.clone(),
+            after: after.clone(),
+            side,
+            ply,
+            capture,
+            check,
+            checkmate: check && !after.has_legal_moves(),
+            promotion: mv.kind().promote(),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use eframe::epaint::Color32;
+use owlchess::{Coord, Move};
+
+/// Width of the arrows unless changed, as a fraction of the cells size.
+const DEFAULT_ARROW_THICKNESS: f32 = 0.075;
+
+/// Arrow drawn over the board, such as a hint or the last move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arrow {
+    pub from: Coord,
+    pub to: Coord,
+    pub color: Color32,
+    /// Width of the arrow, as a fraction of the cells size.
+    pub thickness: f32,
+    /// From 0, invisible, to 1, the colour as it is.
+    pub opacity: f32,
+}
+
+impl Arrow {
+    pub fn new(from: Coord, to: Coord, color: Color32) -> Self {
+        Self {
+            from,
+            to,
+            color,
+            thickness: DEFAULT_ARROW_THICKNESS,
+            opacity: 1.0,
+        }
+    }
+
+    /// Arrow from the start cell to the target cell of a move.
+    pub fn for_move(mv: Move, color: Color32) -> Self {
+        Self::new(mv.src(), mv.dst(), color)
+    }
+
+    pub fn thickness(mut self, thickness: f32) -> Self {
+        self.thickness = thickness;
+        self
+    }
+
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+}
+
+/// Cell filled with a colour over the board, such as the squares a defending king may not leave.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SquareHighlight {
+    pub cell: Coord,
+    pub color: Color32,
+    /// From 0, invisible, to 1, the colour as it is.
+    pub opacity: f32,
+}
+
+impl SquareHighlight {
+    pub fn new(cell: Coord, color: Color32) -> Self {
+        Self {
+            cell,
+            color,
+            opacity: 1.0,
+        }
+    }
+
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+}
@@ -5,10 +5,10 @@ use eframe::{
         Color32, FontId, Mesh, Pos2, Rect, RectShape, Rounding, Shape, Stroke, Vec2,
     },
 };
-use owlchess::{Color, File, Rank};
+use owlchess::{Color, Coord, File, Piece, Rank};
 use std::ops::Add;
 
-use super::{utils::get_piece_type_from, ChessBoard, DndData, Colors};
+use super::{utils::get_piece_type_from, Arrow, ChessBoard, Colors, DndData, PieceRendering};
 
 pub(crate) fn draw_background(ui: &mut Ui, rect: Rect, colors: &Colors) {
     ui.painter().add(Shape::Rect(RectShape {
@@ -30,6 +30,8 @@ pub(crate) fn draw_cells(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
         for col in 0..=7 {
             let file = (if board.reversed { 7 - col } else { col }) as u8;
             let rank = (if board.reversed { 7 - row } else { row }) as u8;
+            // Ranks counted from the first one, as for the start cell and the selected cell.
+            let piece_rank = (if board.reversed { row } else { 7 - row }) as u8;
 
             let white_cell = (col + row) % 2 == 0;
 
@@ -38,8 +40,8 @@ pub(crate) fn draw_cells(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
                     start_file,
                     start_rank,
                     ..
-                }) => file == start_file && rank == start_rank,
-                None => false,
+                }) => file == start_file && piece_rank == start_rank,
+                None => board.selected_cell == Some((file, piece_rank)),
             };
 
             let is_dnd_end_cell = match board.dnd_data {
@@ -117,6 +119,16 @@ pub(crate) fn draw_pieces(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
                 continue;
             }
 
+            let cell = Coord::from_parts(
+                File::from_index(file as usize),
+                Rank::from_index(7 - rank as usize),
+            );
+            if let Some(animation) = board.displayed_animation() {
+                if animation.covers(cell) {
+                    continue;
+                }
+            }
+
             let x = rect.min.x + cells_size * (0.5 + col as f32);
             let y = rect.min.y + cells_size * (0.5 + row as f32);
 
@@ -128,7 +140,7 @@ pub(crate) fn draw_pieces(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
                 },
             };
 
-            let square = board.position.get2(
+            let square = board.displayed_position().get2(
                 File::from_index(file as usize),
                 Rank::from_index(7 - rank as usize),
             );
@@ -136,9 +148,76 @@ pub(crate) fn draw_pieces(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
                 continue;
             }
 
-            let piece_type = square.piece();
-            let piece_color = square.color();
-            let piece_type = get_piece_type_from(piece_type.unwrap(), piece_color.unwrap());
+            draw_piece(
+                ui,
+                piece_rect,
+                board,
+                square.piece().unwrap(),
+                square.color().unwrap(),
+                1.0,
+            );
+        }
+    }
+}
+
+/// Pieces of the last move on their way, over the captured piece fading out.
+pub(crate) fn draw_animation(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
+    let animation = match board.displayed_animation() {
+        Some(animation) => animation,
+        None => return,
+    };
+    let cells_size = rect.size().x * 0.111;
+    let progress = animation.progress(board.animation_duration);
+
+    if let Some(captured) = &animation.captured {
+        let piece_rect = Rect::from_center_size(
+            cell_center(rect, board, captured.from),
+            Vec2::splat(cells_size),
+        );
+        draw_piece(
+            ui,
+            piece_rect,
+            board,
+            captured.piece,
+            captured.color,
+            1.0 - progress,
+        );
+    }
+    for movement in &animation.movements {
+        let from = cell_center(rect, board, movement.from);
+        let to = cell_center(rect, board, movement.to);
+        let center = from + (to - from) * progress;
+        let piece_rect = Rect::from_center_size(center, Vec2::splat(cells_size));
+        draw_piece(ui, piece_rect, board, movement.piece, movement.color, 1.0);
+    }
+}
+
+/// Draws a piece as the pieces display of the board asks: as its image, as a disc, or not at all.
+fn draw_piece(
+    ui: &mut Ui,
+    piece_rect: Rect,
+    board: &ChessBoard,
+    piece: Piece,
+    color: Color,
+    opacity: f32,
+) {
+    match board.piece_rendering(color) {
+        PieceRendering::Hidden => {}
+        PieceRendering::Disc => {
+            let (fill, outline) = match color {
+                Color::White => (Color32::WHITE, Color32::BLACK),
+                Color::Black => (Color32::BLACK, Color32::WHITE),
+            };
+            let radius = piece_rect.width() * 0.35;
+            ui.painter().circle(
+                piece_rect.center(),
+                radius,
+                fill.linear_multiply(opacity),
+                Stroke::new(radius * 0.1, outline.linear_multiply(opacity)),
+            );
+        }
+        PieceRendering::Image => {
+            let piece_type = get_piece_type_from(piece, color);
 
             let image = match piece_type {
                 'P' => &board.pieces_images.wp,
@@ -156,20 +235,122 @@ pub(crate) fn draw_pieces(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
                 _ => panic!("Not recognized piece {}", piece_type),
             };
 
-            {
-                let ctx = ui.ctx();
-                let mut mesh = Mesh::with_texture(image.texture_id(ctx));
-                mesh.add_rect_with_uv(
-                    piece_rect,
-                    Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
-                    Color32::WHITE,
-                );
-                ui.painter().add(mesh);
-            }
+            let ctx = ui.ctx();
+            let mut mesh = Mesh::with_texture(image.texture_id(ctx));
+            mesh.add_rect_with_uv(
+                piece_rect,
+                Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                Color32::WHITE.linear_multiply(opacity),
+            );
+            ui.painter().add(mesh);
+        }
+    }
+}
+
+/// Dots on the cells the selected piece can go to, rings around the pieces it can capture.
+///
+/// Nothing is drawn while pieces are hidden: the moves would tell where they block or pin.
+pub(crate) fn draw_legal_destinations(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
+    if board.hides_pieces() {
+        return;
+    }
+    let size = rect.size().x;
+    let cells_size = size * 0.111;
+
+    for destination in board.legal_destinations() {
+        let center = cell_center(rect, board, destination);
+        if board.position.get(destination).is_occupied() {
+            ui.painter().circle_stroke(
+                center,
+                cells_size * 0.45,
+                Stroke::new(cells_size * 0.08, board.colors.legal_capture_ring),
+            );
+        } else {
+            ui.painter()
+                .circle_filled(center, cells_size * 0.15, board.colors.legal_move_dot);
         }
     }
 }
 
+pub(crate) fn draw_keyboard_cursor(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
+    let size = rect.size().x;
+    let cells_size = size * 0.111;
+
+    let (file, rank) = board.keyboard_cursor();
+    let coord = Coord::from_parts(
+        File::from_index(file as usize),
+        Rank::from_index(7 - rank as usize),
+    );
+    let cell_rect = Rect::from_center_size(
+        cell_center(rect, board, coord),
+        Vec2::splat(cells_size * 0.92),
+    );
+    ui.painter().rect_stroke(
+        cell_rect,
+        Rounding::none(),
+        Stroke::new(cells_size * 0.06, board.colors.keyboard_cursor),
+    );
+}
+
+/// Radial gradient under the king of the side to move when it is in check, darker on checkmate.
+pub(crate) fn draw_king_in_check(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
+    let position = board.displayed_position();
+    let king = position.king_pos(position.side());
+    if !position.is_check() || !board.shows_piece_on(king) {
+        return;
+    }
+    let size = rect.size().x;
+    let cells_size = size * 0.111;
+
+    let color = if position.has_legal_moves() {
+        board.colors.check_gradient
+    } else {
+        board.colors.checkmate_marker
+    };
+    let center = cell_center(rect, board, king);
+    let radius = cells_size * 0.5;
+    let segments = 32;
+
+    let mut mesh = Mesh::default();
+    mesh.colored_vertex(center, color);
+    for segment in 0..segments {
+        let angle = std::f32::consts::TAU * segment as f32 / segments as f32;
+        let point = center + Vec2::angled(angle) * radius;
+        mesh.colored_vertex(point, Color32::TRANSPARENT);
+        let next = (segment + 1) % segments;
+        mesh.add_triangle(0, 1 + segment, 1 + next);
+    }
+    ui.painter().add(mesh);
+}
+
+/// Badge on the corner of the king of the side to move, once it is checkmated or stalemated.
+pub(crate) fn draw_game_end_marker(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
+    let position = board.displayed_position();
+    let king = position.king_pos(position.side());
+    if position.has_legal_moves() || !board.shows_piece_on(king) {
+        return;
+    }
+    let size = rect.size().x;
+    let cells_size = size * 0.111;
+
+    let (color, text) = if position.is_check() {
+        (board.colors.checkmate_marker, "#")
+    } else {
+        (board.colors.stalemate_marker, "=")
+    };
+    let badge_center =
+        cell_center(rect, board, king) + Vec2::new(cells_size * 0.32, -cells_size * 0.32);
+    ui.painter()
+        .circle_filled(badge_center, cells_size * 0.18, color);
+    ui.painter().text(
+        badge_center,
+        eframe::emath::Align2::CENTER_CENTER,
+        text,
+        FontId::proportional(cells_size * 0.28),
+        Color32::WHITE,
+    );
+}
+
 pub(crate) fn draw_coordinates(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
     let size = rect.size().x;
     let cells_size = size * 0.111;
@@ -230,7 +411,7 @@ pub(crate) fn draw_player_turn(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
     let x = rect.min.x + cells_size * 8.75;
     let y = rect.min.y + cells_size * 8.75;
 
-    let white_turn = board.position.side() == Color::White;
+    let white_turn = board.displayed_position().side() == Color::White;
     let color = if white_turn {
         Color32::WHITE
     } else {
@@ -245,24 +426,6 @@ pub(crate) fn draw_moved_piece(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
     let cells_size = size * 0.111;
 
     if let Some(dnd_data) = &board.dnd_data {
-        let piece_type = get_piece_type_from(dnd_data.piece_type, dnd_data.piece_color);
-
-        let image = match piece_type {
-            'P' => &board.pieces_images.wp,
-            'N' => &board.pieces_images.wn,
-            'B' => &board.pieces_images.wb,
-            'R' => &board.pieces_images.wr,
-            'Q' => &board.pieces_images.wq,
-            'K' => &board.pieces_images.wk,
-            'p' => &board.pieces_images.bp,
-            'n' => &board.pieces_images.bn,
-            'b' => &board.pieces_images.bb,
-            'r' => &board.pieces_images.br,
-            'q' => &board.pieces_images.bq,
-            'k' => &board.pieces_images.bk,
-            _ => panic!("Not recognized piece {}", piece_type),
-        };
-
         {
             let piece_rect = if (board.reversed != dnd_data.started_with_reversed_board)
                 && dnd_data.has_pending_promotion
@@ -296,14 +459,14 @@ pub(crate) fn draw_moved_piece(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
                     },
                 }
             };
-            let ctx = ui.ctx();
-            let mut mesh = Mesh::with_texture(image.texture_id(ctx));
-            mesh.add_rect_with_uv(
+            draw_piece(
+                ui,
                 piece_rect,
-                Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
-                Color32::WHITE,
+                board,
+                dnd_data.piece_type,
+                dnd_data.piece_color,
+                1.0,
             );
-            ui.painter().add(mesh);
         }
     }
 }
@@ -424,30 +587,109 @@ pub(crate) fn draw_promotion_buttons(ui: &mut Ui, rect: Rect, board: &mut ChessB
     }
 }
 
+/// Arrows and circles drawn by the user on the displayed position.
+pub(crate) fn draw_annotations(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
+    let size = rect.size().x;
+    let cells_size = size * 0.111;
+
+    let annotations = board.history.annotations();
+    for &(color, cell) in annotations.circles() {
+        ui.painter().circle_stroke(
+            cell_center(rect, board, cell),
+            cells_size * 0.45,
+            Stroke::new(cells_size * 0.07, board.colors.annotation(color)),
+        );
+    }
+    for &(color, from, to) in annotations.arrows() {
+        let arrow = Arrow::new(from, to, board.colors.annotation(color));
+        draw_arrow(ui, rect, board, &arrow);
+    }
+}
+
 pub(crate) fn draw_last_move_arrow(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
-    match &board.last_move_arrow {
-        Some(arrow_coords) => draw_arrow(ui, rect, arrow_coords, board.reversed, board.colors.last_move_arrow),
-        _ => {}
+    if let Some(arrow) = &board.displayed_last_move_arrow() {
+        draw_arrow(ui, rect, board, arrow)
     }
 }
 
-fn draw_arrow(ui: &mut Ui, rect: Rect, arrow: &(u8, u8, u8, u8), reversed: bool, color: Color32) {
+/// Arrows added by the caller of the board.
+pub(crate) fn draw_arrows(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
+    for arrow in &board.arrows {
+        draw_arrow(ui, rect, board, arrow);
+    }
+}
+
+/// Start and target cells of the queued premoves.
+pub(crate) fn draw_premoves(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
+    let size = rect.size().x;
+    let cells_size = size * 0.111;
+
+    for premove in &board.premoves {
+        for (file, rank) in [premove.from, premove.to] {
+            let coord = Coord::from_parts(
+                File::from_index(file as usize),
+                Rank::from_index(7 - rank as usize),
+            );
+            let cell_rect =
+                Rect::from_center_size(cell_center(rect, board, coord), Vec2::splat(cells_size));
+            ui.painter()
+                .rect_filled(cell_rect, Rounding::none(), board.colors.premove);
+        }
+    }
+}
+
+/// Cells highlighted by the caller of the board.
+pub(crate) fn draw_highlights(ui: &mut Ui, rect: Rect, board: &ChessBoard) {
+    let size = rect.size().x;
+    let cells_size = size * 0.111;
+
+    for highlight in &board.highlights {
+        let cell_rect = Rect::from_center_size(
+            cell_center(rect, board, highlight.cell),
+            Vec2::splat(cells_size),
+        );
+        ui.painter().rect_filled(
+            cell_rect,
+            Rounding::none(),
+            highlight.color.linear_multiply(highlight.opacity),
+        );
+    }
+}
+
+/// Center of a cell on the screen, taking the orientation of the board into account.
+fn cell_center(rect: Rect, board: &ChessBoard, coord: Coord) -> Pos2 {
+    let cells_size = rect.size().x * 0.111;
+
+    let file = coord.file().index();
+    // Rank indices start from the eighth rank.
+    let rank = coord.rank().index();
+    let col = if board.reversed { 7 - file } else { file };
+    let row = if board.reversed { 7 - rank } else { rank };
+
+    Pos2 {
+        x: rect.min.x + cells_size * (1.0 + col as f32),
+        y: rect.min.y + cells_size * (1.0 + row as f32),
+    }
+}
+
+fn draw_arrow(ui: &mut Ui, rect: Rect, board: &ChessBoard, arrow: &Arrow) {
     let size = rect.size().x;
     let cells_size = size * 0.111;
 
     let block_size = cells_size;
     let half_block_size = block_size / 2.0;
-
-    let (start_file, start_rank, end_file, end_rank) = arrow;
-    let (start_col, start_row, end_col, end_row) = if reversed {
-        (
-            7 - *start_file,
-            7 - *start_rank,
-            7 - *end_file,
-            7 - *end_rank,
-        )
+    let color = arrow.color.linear_multiply(arrow.opacity);
+    let stroke = Stroke::new(cells_size * arrow.thickness, color);
+
+    // Rank indices start from the eighth rank, as rows do.
+    let start_file = arrow.from.file().index() as u8;
+    let start_rank = arrow.from.rank().index() as u8;
+    let end_file = arrow.to.file().index() as u8;
+    let end_rank = arrow.to.rank().index() as u8;
+    let (start_col, start_row, end_col, end_row) = if board.reversed {
+        (7 - start_file, 7 - start_rank, 7 - end_file, 7 - end_rank)
     } else {
-        (*start_file, *start_rank, *end_file, *end_rank)
+        (start_file, start_rank, end_file, end_rank)
     };
 
     let start_offset = (
@@ -478,20 +720,20 @@ fn draw_arrow(ui: &mut Ui, rect: Rect, arrow: &(u8, u8, u8, u8), reversed: bool,
             Pos2::new(start_offset.0, start_offset.1).add(component_offset),
             Pos2::new(end_offset.0, end_offset.1).add(component_offset),
         ],
-        stroke: Stroke::new(half_block_size * 0.150, color),
+        stroke,
     });
     ui.painter().add(Shape::LineSegment {
         points: [
-            Pos2::new(end_offset.0,end_offset.1).add(component_offset),
+            Pos2::new(end_offset.0, end_offset.1).add(component_offset),
             Pos2::new(arrow_point_1.0, arrow_point_1.1).add(component_offset),
         ],
-        stroke: Stroke::new(half_block_size * 0.150, color),
+        stroke,
     });
     ui.painter().add(Shape::LineSegment {
         points: [
-            Pos2::new(end_offset.0,end_offset.1).add(component_offset),
+            Pos2::new(end_offset.0, end_offset.1).add(component_offset),
             Pos2::new(arrow_point_2.0, arrow_point_2.1).add(component_offset),
         ],
-        stroke: Stroke::new(half_block_size * 0.150, color),
+        stroke,
     });
 }
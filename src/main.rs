@@ -2,11 +2,50 @@ use eframe::{
     egui::{self, ImageButton},
     epaint::{Vec2, Color32},
 };
-use gui::chessboard::{ChessBoard, Colors};
+use endgames::{
+    catalogue::{EndgameType, ExpectedResult, CATALOGUE},
+    generator::{GenerationError, MaterialSpec, PositionGenerator},
+    goal::{Goal, Judge, Verdict},
+};
+use engine::{
+    search::Search, tablebase::TablebaseEngine, uci::UciEngine, ComputerPlayer, Engine,
+};
+use gui::chessboard::{
+    describe_outcome, Arrow, ChessBoard, Colors, MoveEvent, PiecesDisplay, PlayerMode,
+    SquareHighlight,
+};
+use owlchess::{Board, Color, Outcome};
+use pgn::{
+    lesson::{Lesson, MoveCheck},
+    writer::PgnGame,
+};
+use std::{
+    fs,
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+use tablebase::{
+    native::{Dtm, NativeTablebase},
+    syzygy::SyzygyTablebase,
+    Tablebase, Wdl,
+};
 
+mod endgames;
+mod engine;
 mod gui;
+mod pgn;
+mod tablebase;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [flag, dir] = args.as_slice() {
+        if flag == "--generate-tablebases" {
+            generate_tablebases(dir);
+            return;
+        }
+    }
+
     let options = eframe::NativeOptions::default();
     eframe::run_native(
         "Basic chess endgames",
@@ -15,27 +54,650 @@ fn main() {
     );
 }
 
+/// Generates the native tables of every catalogue endgame small enough, and saves them in `dir`.
+fn generate_tablebases(dir: &str) {
+    let mut tablebase = NativeTablebase::default();
+    for endgame in CATALOGUE {
+        let material: MaterialSpec = endgame
+            .material
+            .parse()
+            .expect("catalogue materials are valid");
+        match tablebase.generate(&material) {
+            Ok(generated) => {
+                for name in generated {
+                    println!("Generated {}", name);
+                }
+            }
+            Err(err) => println!("Skipped {} : {}", endgame.id, err),
+        }
+    }
+    if let Err(err) = tablebase.save_directory(dir) {
+        eprintln!("Cannot save tables : {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Time given to the computer for each of its moves.
+const COMPUTER_MOVE_TIME: Duration = Duration::from_secs(1);
+/// Seeds tried in turn, until the tables agree the position has the expected result.
+const SEED_ATTEMPTS: u64 = 100;
+/// Moves given to the player on top of the shortest mate, when the tables know it.
+const MATE_WITHIN_SLACK: u32 = 5;
+/// Where the tables written by `--generate-tablebases` are looked for by default.
+const DEFAULT_NATIVE_TABLEBASES_DIR: &str = "tablebases";
+/// How long the position is shown in the flash blindfold exercise, unless changed.
+const DEFAULT_FLASH_SECONDS: u64 = 5;
+/// Lesson proposed on the selection screen, among the ones shipped in the `lessons` directory.
+const DEFAULT_LESSON_PATH: &str = "lessons/lucena.pgn";
+
+enum Screen {
+    Selection,
+    Game,
+    Lesson,
+}
+
+/// Visualisation exercise chosen on the selection screen.
+#[derive(PartialEq, Clone, Copy)]
+enum Blindfold {
+    Off,
+    HideAll,
+    HideOpponent,
+    Discs,
+    Flash,
+}
+
+#[derive(PartialEq)]
+enum Opponent {
+    BuiltIn,
+    Uci,
+}
+
 struct MyApp {
     board: ChessBoard,
+    screen: Screen,
+    endgame: Option<&'static EndgameType>,
+    seed_input: String,
+    seed: u64,
+    error: Option<String>,
+    opponent: Opponent,
+    blindfold: Blindfold,
+    flash_seconds: u64,
+    uci_engine_path: String,
+    native_tablebases_path: String,
+    syzygy_path: String,
+    computer: Option<ComputerPlayer>,
+    computer_side: Option<Color>,
+    /// Positions the board asks the computer to play in.
+    computer_to_move: Receiver<Board>,
+    /// Moves played on the board, checked against the lesson.
+    moves_done: Receiver<MoveEvent>,
+    /// Results of the games ended by a move on the board.
+    games_over: Receiver<Outcome>,
+    /// Result of the game on the board, once it is over.
+    outcome: Option<Outcome>,
+    judge: Option<Judge>,
+    verdict: Option<Verdict>,
+    /// Verdict of the drill left last, shown on the selection screen.
+    previous_verdict: Option<Verdict>,
+    lesson_path: String,
+    lesson: Option<Lesson>,
+    /// Position typed on the selection screen, to play freely from it.
+    fen_input: String,
+    /// Feedback on the last move of the student.
+    lesson_feedback: Option<(Color32, String)>,
+    /// Whether the next move of the lesson is shown to the student.
+    lesson_hint: bool,
 }
 
 impl Default for MyApp {
     fn default() -> Self {
+        let (moves_done_sender, moves_done) = mpsc::channel();
+        let mut board = ChessBoard::new(
+            500.0,
+            Box::new(move |event| {
+                // The receiver lives as long as the board, in the same app.
+                let _ = moves_done_sender.send(event.clone());
+            }),
+        );
+        let (games_over_sender, games_over) = mpsc::channel();
+        board.set_on_game_over(Box::new(move |outcome| {
+            // The receiver lives as long as the board, in the same app.
+            let _ = games_over_sender.send(*outcome);
+        }));
+        let (computer_to_move_sender, computer_to_move) = mpsc::channel();
+        board.set_on_computer_to_move(Box::new(move |position| {
+            // The receiver lives as long as the board, in the same app.
+            let _ = computer_to_move_sender.send(position.clone());
+        }));
         Self {
-            board: ChessBoard::new(
-                500.0,
-                Box::new(|move_san|{
-                    println!("{}",*move_san);
-                })
-            ),
+            board,
+            screen: Screen::Selection,
+            endgame: None,
+            seed_input: String::new(),
+            seed: 0,
+            error: None,
+            opponent: Opponent::BuiltIn,
+            blindfold: Blindfold::Off,
+            flash_seconds: DEFAULT_FLASH_SECONDS,
+            uci_engine_path: String::new(),
+            native_tablebases_path: String::from(DEFAULT_NATIVE_TABLEBASES_DIR),
+            syzygy_path: String::new(),
+            computer: None,
+            computer_side: None,
+            computer_to_move,
+            moves_done,
+            games_over,
+            outcome: None,
+            judge: None,
+            verdict: None,
+            previous_verdict: None,
+            lesson_path: String::from(DEFAULT_LESSON_PATH),
+            lesson: None,
+            fen_input: String::new(),
+            lesson_feedback: None,
+            lesson_hint: false,
         }
     }
 }
 
-impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+impl MyApp {
+    /// Seed typed on the selection screen, or a random one if none was given.
+    fn chosen_seed(&mut self) -> Option<u64> {
+        match self.seed_input.trim() {
+            "" => Some(random_seed()),
+            input => match input.parse() {
+                Ok(seed) => Some(seed),
+                Err(_) => {
+                    self.error = Some(format!("Invalid seed : {}", input));
+                    None
+                }
+            },
+        }
+    }
+
+    fn start_endgame(&mut self, endgame: &'static EndgameType, seed: u64) {
+        let uci_engine_path = match self.opponent {
+            Opponent::BuiltIn => None,
+            Opponent::Uci => Some(self.uci_engine_path.trim().to_string()),
+        };
+        let player = endgame.player_side();
+        let mut native = None;
+        // Missing native tables are not an error: they have to be generated first.
+        if Path::new(self.native_tablebases_path.trim()).is_dir() {
+            match NativeTablebase::load_directory(self.native_tablebases_path.trim()) {
+                Ok(tables) => native = Some(tables),
+                Err(err) => {
+                    self.error = Some(format!("Cannot load native tables : {}", err));
+                    return;
+                }
+            }
+        }
+        let mut syzygy = None;
+        if !self.syzygy_path.trim().is_empty() {
+            match SyzygyTablebase::open(self.syzygy_path.trim()) {
+                Ok(tables) => syzygy = Some(tables),
+                Err(err) => {
+                    self.error = Some(format!("Cannot open syzygy tables : {}", err));
+                    return;
+                }
+            }
+        }
+
+        let tables = native
+            .iter()
+            .map(|tables| tables as &dyn Tablebase)
+            .chain(syzygy.iter().map(|tables| tables as &dyn Tablebase));
+        let (seed, position) = match generate_position(endgame, seed, &tables.collect::<Vec<_>>()) {
+            Ok(Some(generated)) => generated,
+            Ok(None) => {
+                self.error = Some(format!(
+                    "The tables give no position where {} can {}.",
+                    player.as_long_str(),
+                    endgame.goal
+                ));
+                return;
+            }
+            Err(err) => {
+                self.error = Some(err.to_string());
+                return;
+            }
+        };
+        let mut goal = endgame.goal;
+        let mut tablebases: Vec<Box<dyn Tablebase>> = vec![];
+        if let Some(native) = native {
+            if goal == Goal::Mate {
+                if let Some(moves) = shortest_mate(&native, &position, player) {
+                    goal = Goal::MateWithin(moves + MATE_WITHIN_SLACK);
+                }
+            }
+            tablebases.push(Box::new(native));
+        }
+        if let Some(syzygy) = syzygy {
+            tablebases.push(Box::new(syzygy));
+        }
+        // Starting an external engine may take a while, so it is done off the frame loop.
+        let computer = ComputerPlayer::new(move || {
+            let engine: Box<dyn Engine> = match uci_engine_path {
+                Some(path) => Box::new(UciEngine::start(path, COMPUTER_MOVE_TIME)?),
+                None => Box::new(Search::new(COMPUTER_MOVE_TIME, 64)),
+            };
+            Ok(Box::new(TablebaseEngine::new(tablebases, engine)))
+        });
+
+        self.leave_drill();
+        self.judge = Some(Judge::new(goal, player, &position));
+        self.verdict = None;
+        // Set first, so that the new position is sent to the computer if it starts.
+        self.board.set_player_mode(PlayerMode::human(player));
+        self.board.set_position(position);
+        self.board.set_pieces_display(self.pieces_display(player));
+        self.outcome = self.board.outcome();
+        self.endgame = Some(endgame);
+        self.seed = seed;
+        self.computer = Some(computer);
+        self.computer_side = Some(player.inv());
+        self.error = None;
+        self.screen = Screen::Game;
+    }
+
+    /// How the board shows the pieces to `player`, for the chosen blindfold exercise.
+    fn pieces_display(&self, player: Color) -> PiecesDisplay {
+        match self.blindfold {
+            Blindfold::Off => PiecesDisplay::Visible,
+            Blindfold::HideAll => PiecesDisplay::Hidden,
+            Blindfold::HideOpponent => PiecesDisplay::HideOpponent(player),
+            Blindfold::Discs => PiecesDisplay::Discs,
+            Blindfold::Flash => PiecesDisplay::Flash(Duration::from_secs(self.flash_seconds)),
+        }
+    }
+
+    /// Gives up the current drill, if it is not decided yet.
+    fn leave_drill(&mut self) {
+        if let Some(judge) = self.judge.take() {
+            let verdict = self.verdict.take().unwrap_or_else(|| judge.abandon());
+            self.previous_verdict = Some(verdict);
+        }
+    }
+
+    fn update_verdict(&mut self) {
+        if self.verdict.is_some() {
+            return;
+        }
+        if let Some(judge) = &self.judge {
+            self.verdict = judge.judge(self.board.position(), self.outcome);
+        }
+    }
+
+    fn show_selection(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Choose an endgame to practise");
+                ui.horizontal(|ui| {
+                    ui.label("Seed (leave empty for a random position) :");
+                    ui.text_edit_singleline(&mut self.seed_input);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Computer opponent :");
+                    ui.radio_value(&mut self.opponent, Opponent::BuiltIn, "Built-in engine");
+                    ui.radio_value(&mut self.opponent, Opponent::Uci, "UCI engine");
+                    if self.opponent == Opponent::Uci {
+                        ui.label("Executable :");
+                        ui.text_edit_singleline(&mut self.uci_engine_path);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Pieces :");
+                    ui.radio_value(&mut self.blindfold, Blindfold::Off, "Visible");
+                    ui.radio_value(&mut self.blindfold, Blindfold::HideAll, "Hidden");
+                    ui.radio_value(
+                        &mut self.blindfold,
+                        Blindfold::HideOpponent,
+                        "Opponent's hidden",
+                    );
+                    ui.radio_value(&mut self.blindfold, Blindfold::Discs, "Discs");
+                    ui.radio_value(&mut self.blindfold, Blindfold::Flash, "Shown for");
+                    ui.add(
+                        egui::DragValue::new(&mut self.flash_seconds)
+                            .clamp_range(1..=60)
+                            .suffix(" s"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Move animation :");
+                    let mut millis = self.board.animation_duration().as_millis() as u64;
+                    let animation = egui::DragValue::new(&mut millis)
+                        .clamp_range(0..=1000)
+                        .suffix(" ms");
+                    if ui.add(animation).changed() {
+                        self.board
+                            .set_animation_duration(Duration::from_millis(millis));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Native tablebases directory (see --generate-tablebases) :");
+                    ui.text_edit_singleline(&mut self.native_tablebases_path);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Syzygy tablebases directory (optional) :");
+                    ui.text_edit_singleline(&mut self.syzygy_path);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Lesson (PGN file) :");
+                    ui.text_edit_singleline(&mut self.lesson_path);
+                    if ui.button("Open lesson").clicked() {
+                        self.open_lesson();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Free play from a position (FEN) :");
+                    ui.text_edit_singleline(&mut self.fen_input);
+                    if ui.button("Set up").clicked() {
+                        self.start_free_play();
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                if let Some(verdict) = self.previous_verdict {
+                    ui.label(format!("Previous drill : {}", verdict));
+                }
+                ui.separator();
+
+                let mut chosen = None;
+                for endgame in CATALOGUE {
+                    let goal = match endgame.expected_result {
+                        ExpectedResult::Win => "win",
+                        ExpectedResult::Draw => "draw",
+                    };
+                    let text = format!(
+                        "{} ({}) : play {} to {}",
+                        endgame.name,
+                        endgame.id,
+                        endgame.player_side().as_long_str(),
+                        goal
+                    );
+                    if ui.button(text).clicked() {
+                        chosen = Some(endgame);
+                    }
+                }
+                if let Some(endgame) = chosen {
+                    if let Some(seed) = self.chosen_seed() {
+                        self.start_endgame(endgame, seed);
+                    }
+                }
+            });
+        });
+    }
+
+    /// Opens the game screen on the typed position, the user moving both sides.
+    fn start_free_play(&mut self) {
+        // Set first, so that the new position is not sent to the computer.
+        self.board.set_player_mode(PlayerMode::Both);
+        if let Err(err) = self.board.set_position_fen(&self.fen_input) {
+            self.error = Some(format!("Cannot set up the position : {}", err));
+            return;
+        }
+        self.board.set_pieces_display(PiecesDisplay::Visible);
+        self.outcome = self.board.outcome();
+        self.endgame = None;
+        self.computer = None;
+        self.computer_side = None;
+        self.error = None;
+        self.screen = Screen::Game;
+    }
+
+    fn open_lesson(&mut self) {
+        let path = self.lesson_path.trim();
+        let lesson = match fs::read_to_string(path) {
+            Ok(pgn) => Lesson::from_pgn(&pgn).map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        match lesson {
+            Ok(lesson) => {
+                self.board
+                    .set_player_mode(PlayerMode::human(lesson.student()));
+                self.board.set_position(lesson.start().clone());
+                self.board
+                    .set_pieces_display(self.pieces_display(lesson.student()));
+                self.lesson = Some(lesson);
+                self.lesson_feedback = None;
+                self.error = None;
+                self.screen = Screen::Lesson;
+            }
+            Err(err) => self.error = Some(format!("Cannot open lesson {} : {}", path, err)),
+        }
+    }
+
+    /// Checks the moves the student just played on the board, and answers them.
+    fn update_lesson(&mut self) {
+        let events: Vec<MoveEvent> = self.moves_done.try_iter().collect();
+        for event in events {
+            self.check_lesson_move(&event);
+        }
+    }
+
+    fn check_lesson_move(&mut self, event: &MoveEvent) {
+        let lesson = match &mut self.lesson {
+            Some(lesson) => lesson,
+            None => return,
+        };
+        // The moves of the lesson itself are played once it has stepped forward.
+        if event.before != *lesson.position() {
+            return;
+        }
+        self.lesson_hint = false;
+        match lesson.try_move(event.mv) {
+            MoveCheck::Wrong => {
+                self.board.undo();
+                let message = format!("{} is not what the lesson expects, try again.", event.fan);
+                self.lesson_feedback = Some((Color32::RED, message));
+                return;
+            }
+            MoveCheck::Mainline => {
+                let message = format!("{} is right.", event.fan);
+                self.lesson_feedback = Some((Color32::from_rgb(12, 200, 12), message));
+            }
+            MoveCheck::Variation => {
+                let message = format!("{} is also possible, let us follow this line.", event.fan);
+                self.lesson_feedback = Some((Color32::from_rgb(12, 200, 12), message));
+            }
+        }
+        if !lesson.is_student_turn() {
+            if let Some(reply) = lesson.step_forward() {
+                self.board.play_move(reply);
+            }
+        }
+    }
+
+    fn show_lesson(&mut self, ctx: &egui::Context) {
+        self.update_lesson();
+        let lesson = match &mut self.lesson {
+            Some(lesson) => lesson,
+            None => return,
+        };
+        self.board.clear_arrows();
+        self.board.clear_highlights();
+        if let (true, Some(hint)) = (self.lesson_hint, lesson.next_move()) {
+            self.board.add_highlight(
+                SquareHighlight::new(hint.src(), Color32::from_rgb(255, 215, 0)).opacity(0.5),
+            );
+            self.board.add_arrow(
+                Arrow::for_move(hint, Color32::from_rgb(255, 140, 0))
+                    .thickness(0.12)
+                    .opacity(0.8),
+            );
+        }
+        egui::SidePanel::right("lesson")
+            .min_width(250.0)
+            .show(ctx, |ui| {
+                ui.heading(lesson.title().unwrap_or("Lesson"));
+                ui.label(format!("You play {}.", lesson.student().as_long_str()));
+                ui.separator();
+                if let Some(san) = lesson.last_san() {
+                    ui.strong(san);
+                }
+                if let Some(comment) = lesson.comment() {
+                    ui.label(comment);
+                }
+                if let Some((color, feedback)) = &self.lesson_feedback {
+                    ui.colored_label(*color, feedback);
+                }
+                if lesson.is_finished() {
+                    ui.label(egui::RichText::new("End of the lesson").strong());
+                } else if lesson.is_student_turn() {
+                    ui.label("Your move.");
+                }
+            });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Choose another endgame").clicked() {
+                        self.screen = Screen::Selection;
+                    }
+                    let previous = egui::Button::new("Previous");
+                    if ui.add_enabled(self.board.can_undo(), previous).clicked()
+                        && lesson.step_back()
+                    {
+                        self.board.undo();
+                        self.lesson_feedback = None;
+                        self.lesson_hint = false;
+                    }
+                    let next = egui::Button::new("Next");
+                    if ui.add_enabled(!lesson.is_finished(), next).clicked() {
+                        if let Some(mv) = lesson.step_forward() {
+                            self.board.play_move(mv);
+                            self.lesson_feedback = None;
+                            self.lesson_hint = false;
+                        }
+                    }
+                    let hint = egui::Button::new("Hint");
+                    let can_hint = lesson.is_student_turn() && !lesson.is_finished();
+                    if ui.add_enabled(can_hint, hint).clicked() {
+                        self.lesson_hint = true;
+                    }
+                    if ui.button("Restart").clicked() {
+                        lesson.restart();
+                        self.board.set_position(lesson.start().clone());
+                        self.lesson_feedback = None;
+                        self.lesson_hint = false;
+                    }
+                    if let PiecesDisplay::Flash(_) = self.board.pieces_display() {
+                        if ui.button("Show the pieces").clicked() {
+                            self.board.flash_pieces();
+                        }
+                    }
+                });
+                ui.add(self.board.widget());
+                ui.add(self.board.move_entry_widget());
+            });
+        });
+    }
+
+    /// Plays the move the computer found, if it has finished thinking.
+    fn update_computer(&mut self) {
+        let computer = match &mut self.computer {
+            Some(computer) => computer,
+            None => return,
+        };
+
+        match computer.poll() {
+            // The reply is stale if the position changed in the meantime.
+            Some(Ok((position, best_move))) if &position == self.board.position() => {
+                let error = match best_move {
+                    Some(best_move) if self.board.play_move(best_move) => None,
+                    Some(best_move) => Some(format!(
+                        "The computer played an illegal move : {}",
+                        best_move
+                    )),
+                    None => Some(String::from("The computer could not find a move.")),
+                };
+                if error.is_some() {
+                    self.error = error;
+                    self.computer_side = None;
+                    self.board.set_player_mode(PlayerMode::Both);
+                }
+            }
+            Some(Err(err)) => {
+                self.error = Some(format!("The computer cannot play : {}", err));
+                self.computer_side = None;
+                self.board.set_player_mode(PlayerMode::Both);
+            }
+            _ => {}
+        }
+    }
+
+    /// Lets the computer think in the positions the board asked it to play in.
+    fn ask_computer(&mut self, ctx: &egui::Context) {
+        let computer = match &mut self.computer {
+            Some(computer) => computer,
+            None => return,
+        };
+
+        // Only the last position matters, the board has left the other ones.
+        if let Some(position) = self.computer_to_move.try_iter().last() {
+            if &position == self.board.position() {
+                let start = self.board.start_position().clone();
+                computer.start_thinking(start, self.board.moves());
+            }
+        }
+        if computer.is_thinking() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+    }
+
+    fn show_game(&mut self, ctx: &egui::Context) {
+        self.update_computer();
+        self.update_outcome(ctx);
+        self.update_verdict();
+        egui::SidePanel::right("history")
+            .min_width(150.0)
+            .show(ctx, |ui| {
+                ui.add(self.board.history_widget());
+            });
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical_centered(|ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Choose another endgame").clicked() {
+                        self.leave_drill();
+                        self.screen = Screen::Selection;
+                    }
+                    if ui.button("Copy FEN").clicked() {
+                        ui.output().copied_text = self.board.position_fen();
+                    }
+                    if ui.button("Copy PGN").clicked() {
+                        ui.output().copied_text = self.pgn();
+                    }
+                    if ui.button("Clear arrows").clicked() {
+                        self.board.clear_annotations();
+                    }
+                    let take_back = egui::Button::new("Take back");
+                    if ui.add_enabled(self.board.can_undo(), take_back).clicked() {
+                        self.take_back();
+                    }
+                    let redo = egui::Button::new("Redo");
+                    if ui.add_enabled(self.board.can_redo(), redo).clicked() {
+                        self.redo();
+                    }
+                    if let PiecesDisplay::Flash(_) = self.board.pieces_display() {
+                        if ui.button("Show the pieces").clicked() {
+                            self.board.flash_pieces();
+                        }
+                    }
+                    if let (Some(endgame), Some(judge)) = (self.endgame, &self.judge) {
+                        ui.label(format!(
+                            "{} - seed {} - goal : {}",
+                            endgame.name,
+                            self.seed,
+                            judge.goal()
+                        ));
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                if self.verdict.is_some() || self.outcome.is_some() {
+                    self.show_result_banner(ui);
+                }
                 ui.vertical_centered(|ui| {
                     let buttons_size = 50.0;
                     let reverse_image = egui_extras::RetainedImage::from_svg_bytes(
@@ -61,8 +723,172 @@ impl eframe::App for MyApp {
                     board_colors.set_background(Color32::from_rgb(80, 150, 50));
                     self.board.set_colors(board_colors);
                     ui.add(self.board.widget());
+                    ui.add(self.board.move_entry_widget());
                 });
             });
         });
+        // Read once the board is shown, as a move dropped on it does not trigger a repaint.
+        self.ask_computer(ctx);
+        self.update_outcome(ctx);
+    }
+
+    /// Keeps the result of the game ended by the last move, shown on the next frame.
+    fn update_outcome(&mut self, ctx: &egui::Context) {
+        if let Some(outcome) = self.games_over.try_iter().last() {
+            self.outcome = Some(outcome);
+            ctx.request_repaint();
+        }
+    }
+
+    /// The game played so far, tagged with the endgame and the goal of the drill.
+    fn pgn(&self) -> String {
+        let mut game = PgnGame::new(
+            self.board.start_position(),
+            &self.board.moves(),
+            self.board.outcome(),
+        );
+        if let Some(computer_side) = self.computer_side {
+            let (white, black) = match computer_side {
+                Color::White => ("Computer", "Player"),
+                Color::Black => ("Player", "Computer"),
+            };
+            game = game.tag("White", white).tag("Black", black);
+        }
+        if let Some(endgame) = self.endgame {
+            game = game
+                .tag("Endgame", endgame.id)
+                .tag("Seed", &self.seed.to_string());
+        }
+        if let Some(judge) = &self.judge {
+            game = game.tag("Goal", &judge.goal().to_string());
+        }
+        for (ply, commands) in self.board.annotation_comments() {
+            game = game.comment(ply, &commands);
+        }
+        game.write()
+    }
+
+    /// Takes back moves until the player is to move again, and gives the drill another chance.
+    fn take_back(&mut self) {
+        let player_turn = self.computer_side != Some(self.board.position().side());
+        let taken_back = if player_turn {
+            self.board.take_back_pair()
+        } else {
+            self.board.undo()
+        };
+        if taken_back {
+            self.verdict = None;
+            self.outcome = None;
+        }
+    }
+
+    /// Plays again the moves taken back, up to the player's next turn.
+    fn redo(&mut self) {
+        if self.board.redo()
+            && self.computer_side == Some(self.board.position().side())
+            && self.board.can_redo()
+        {
+            self.board.redo();
+        }
+        // Redoing does not end the game again, the board only tells when a move is played.
+        self.outcome = self.board.outcome();
+    }
+
+    fn show_result_banner(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            if let Some(verdict) = self.verdict {
+                let color = match verdict {
+                    Verdict::Success => Color32::from_rgb(12, 200, 12),
+                    Verdict::Failed(_) => Color32::RED,
+                };
+                ui.colored_label(color, egui::RichText::new(verdict.to_string()).heading());
+            }
+            if let Some(outcome) = self.outcome {
+                ui.label(describe_outcome(&outcome));
+            }
+            // Free play has no endgame to start again.
+            let endgame = match self.endgame {
+                Some(endgame) => endgame,
+                None => return,
+            };
+            ui.horizontal(|ui| {
+                if ui.button("New position").clicked() {
+                    self.start_endgame(endgame, random_seed());
+                }
+                if ui.button("Retry same position").clicked() {
+                    self.start_endgame(endgame, self.seed);
+                }
+            });
+        });
+    }
+}
+
+/// Generates the position of `endgame` for `seed`, or for the next seeds when the tables say
+/// the player cannot get the expected result from it. Returns the seed used with the position.
+fn generate_position(
+    endgame: &EndgameType,
+    seed: u64,
+    tables: &[&dyn Tablebase],
+) -> Result<Option<(u64, Board)>, GenerationError> {
+    let rules = endgame.generation_rules();
+    let player = endgame.player_side();
+    for seed in (0..SEED_ATTEMPTS).map(|offset| seed.wrapping_add(offset)) {
+        let position = PositionGenerator::new(seed).generate(&rules)?;
+        let probe = match tables.iter().find_map(|tables| tables.probe(&position)) {
+            Some(probe) => probe,
+            None => return Ok(Some((seed, position))),
+        };
+        let wdl = if position.side() == player {
+            probe.wdl
+        } else {
+            probe.wdl.inv()
+        };
+        let expected = match endgame.expected_result {
+            ExpectedResult::Win => wdl == Wdl::Win,
+            ExpectedResult::Draw => wdl != Wdl::Loss,
+        };
+        if expected {
+            return Ok(Some((seed, position)));
+        }
+    }
+    Ok(None)
+}
+
+/// Number of moves the player needs to mate, if the tables know the position is won for them.
+fn shortest_mate(native: &NativeTablebase, position: &Board, player: Color) -> Option<u32> {
+    let dtm = native.probe_dtm(position)?;
+    let player_wins = match dtm {
+        Dtm::Win(_) => position.side() == player,
+        Dtm::Loss(_) => position.side() != player,
+        Dtm::Draw => false,
+    };
+    if player_wins {
+        dtm.moves_to_mate().map(u32::from)
+    } else {
+        None
+    }
+}
+
+/// Kept small, so that it stays easy to note down and type back.
+fn random_seed() -> u64 {
+    rand::random::<u32>() as u64
+}
+
+impl eframe::App for MyApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Only the game asks the computer to move and shows the results, and only the lesson
+        // looks at the moves played.
+        if !matches!(self.screen, Screen::Game) {
+            self.computer_to_move.try_iter().for_each(drop);
+            self.games_over.try_iter().for_each(drop);
+        }
+        if !matches!(self.screen, Screen::Lesson) {
+            self.moves_done.try_iter().for_each(drop);
+        }
+        match self.screen {
+            Screen::Selection => self.show_selection(ctx),
+            Screen::Game => self.show_game(ctx),
+            Screen::Lesson => self.show_lesson(ctx),
+        }
     }
 }
@@ -0,0 +1,118 @@
+use owlchess::{Board, Color, Move};
+
+use super::parser::{self, GameTree, PgnError};
+
+/// How a move of the student compares with the lesson.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveCheck {
+    Mainline,
+    Variation,
+    Wrong,
+}
+
+/// Walks a student through an annotated game, from the position of its FEN tag.
+///
+/// The student plays the side to move in the starting position, and any move of the mainline
+/// or of a variation is accepted.
+pub struct Lesson {
+    tree: GameTree,
+    current: usize,
+}
+
+impl Lesson {
+    pub fn from_pgn(pgn: &str) -> Result<Self, PgnError> {
+        Ok(Self {
+            tree: parser::parse(pgn)?,
+            current: GameTree::ROOT,
+        })
+    }
+
+    /// Value of the `Event` tag, which names the lesson.
+    pub fn title(&self) -> Option<&str> {
+        self.tree.tag("Event")
+    }
+
+    pub fn start(&self) -> &Board {
+        &self.tree.node(GameTree::ROOT).position
+    }
+
+    pub fn position(&self) -> &Board {
+        &self.tree.node(self.current).position
+    }
+
+    pub fn student(&self) -> Color {
+        self.start().side()
+    }
+
+    pub fn is_student_turn(&self) -> bool {
+        self.position().side() == self.student()
+    }
+
+    /// Comments on the last move, the one given before it first.
+    pub fn comment(&self) -> Option<String> {
+        let node = self.tree.node(self.current);
+        let comments: Vec<&str> = [&node.comment_before, &node.comment]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        (!comments.is_empty()).then(|| comments.join(" "))
+    }
+
+    /// SAN of the last move, `None` in the starting position.
+    pub fn last_san(&self) -> Option<&str> {
+        let node = self.tree.node(self.current);
+        node.mv.map(|_| node.san.as_str())
+    }
+
+    /// The next move of the line being followed, if it goes on.
+    pub fn next_move(&self) -> Option<Move> {
+        let next = *self.tree.node(self.current).children.first()?;
+        self.tree.node(next).mv
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tree.node(self.current).children.is_empty()
+    }
+
+    /// Plays the next move of the line being followed, and returns it.
+    pub fn step_forward(&mut self) -> Option<Move> {
+        let next = *self.tree.node(self.current).children.first()?;
+        self.current = next;
+        self.tree.node(next).mv
+    }
+
+    /// Goes back one move, and returns whether there was one.
+    pub fn step_back(&mut self) -> bool {
+        match self.tree.node(self.current).parent {
+            Some(parent) => {
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.current = GameTree::ROOT;
+    }
+
+    /// Checks a move of the student, and follows its line if the lesson knows it.
+    pub fn try_move(&mut self, mv: Move) -> MoveCheck {
+        let children = &self.tree.node(self.current).children;
+        match children
+            .iter()
+            .position(|&child| self.tree.node(child).mv == Some(mv))
+        {
+            Some(rank) => {
+                self.current = children[rank];
+                if rank == 0 {
+                    MoveCheck::Mainline
+                } else {
+                    MoveCheck::Variation
+                }
+            }
+            None => MoveCheck::Wrong,
+        }
+    }
+}
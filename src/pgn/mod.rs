@@ -0,0 +1,3 @@
+pub mod lesson;
+pub mod parser;
+pub mod writer;
@@ -0,0 +1,431 @@
+use std::fmt;
+
+use owlchess::{board::FenParseError, moves::san, Board, Move};
+
+#[derive(Debug)]
+pub enum PgnError {
+    InvalidTag(String),
+    InvalidFen(FenParseError),
+    UnclosedComment,
+    UnbalancedVariation,
+    /// A variation must replace a move, so it cannot come before the first one.
+    VariationWithoutMove,
+    IllegalMove(String),
+    NoMoves,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTag(tag) => write!(f, "invalid tag : {}", tag),
+            Self::InvalidFen(err) => write!(f, "invalid FEN tag : {}", err),
+            Self::UnclosedComment => write!(f, "comment not closed"),
+            Self::UnbalancedVariation => write!(f, "parentheses of variations do not match"),
+            Self::VariationWithoutMove => write!(f, "variation before any move"),
+            Self::IllegalMove(san) => write!(f, "illegal or unknown move : {}", san),
+            Self::NoMoves => write!(f, "the game has no moves"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+/// One position of a game tree, with the move which led to it.
+#[derive(Debug)]
+pub struct Node {
+    /// `None` for the starting position.
+    pub mv: Option<Move>,
+    pub san: String,
+    pub position: Board,
+    /// Numeric annotation glyphs, with move suffixes such as "!?" converted to their NAG.
+    pub nags: Vec<u8>,
+    /// Comment given before the move, at the start of a variation.
+    pub comment_before: Option<String>,
+    pub comment: Option<String>,
+    pub parent: Option<usize>,
+    /// The main continuation first, then the variations.
+    pub children: Vec<usize>,
+}
+
+/// The first game of a PGN text, with all its variations.
+#[derive(Debug)]
+pub struct GameTree {
+    pub tags: Vec<(String, String)>,
+    nodes: Vec<Node>,
+}
+
+impl GameTree {
+    pub const ROOT: usize = 0;
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn node(&self, index: usize) -> &Node {
+        &self.nodes[index]
+    }
+
+    fn add_move(&mut self, parent: usize, mv: Move, san: String) -> usize {
+        let position = self.nodes[parent]
+            .position
+            .make_move(mv)
+            .expect("parsed moves are legal");
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            mv: Some(mv),
+            san,
+            position,
+            nags: vec![],
+            comment_before: None,
+            comment: None,
+            parent: Some(parent),
+            children: vec![],
+        });
+        self.nodes[parent].children.push(index);
+        index
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Nag(u8),
+    San(String),
+    Result,
+}
+
+/// Parses the first game of a PGN text: its tags, moves, comments, NAGs and nested variations.
+///
+/// Games starting from another position than the initial one must have a `FEN` tag.
+pub fn parse(pgn: &str) -> Result<GameTree, PgnError> {
+    let tokens = tokenize(pgn)?;
+
+    let mut tags = vec![];
+    let mut movetext = tokens.into_iter().peekable();
+    while let Some(Token::Tag(..)) = movetext.peek() {
+        if let Some(Token::Tag(name, value)) = movetext.next() {
+            tags.push((name, value));
+        }
+    }
+    let start = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => Board::from_fen(fen).map_err(PgnError::InvalidFen)?,
+        None => Board::initial(),
+    };
+
+    let mut tree = GameTree {
+        tags,
+        nodes: vec![Node {
+            mv: None,
+            san: String::new(),
+            position: start,
+            nags: vec![],
+            comment_before: None,
+            comment: None,
+            parent: None,
+            children: vec![],
+        }],
+    };
+
+    // Node after which the next move is played, and the ones to come back to after variations.
+    let mut current = GameTree::ROOT;
+    let mut variations = vec![];
+    let mut pending_comment = None;
+    for token in movetext {
+        match token {
+            Token::Tag(name, _) => return Err(PgnError::InvalidTag(name)),
+            Token::Comment(comment) => {
+                // Right after "(", the next move replaces a continuation which is already known.
+                if tree.nodes[current].children.is_empty() {
+                    append(&mut tree.nodes[current].comment, comment);
+                } else {
+                    append(&mut pending_comment, comment);
+                }
+            }
+            Token::VariationStart => {
+                let parent = tree.nodes[current]
+                    .parent
+                    .ok_or(PgnError::VariationWithoutMove)?;
+                variations.push(current);
+                current = parent;
+            }
+            Token::VariationEnd => {
+                current = variations.pop().ok_or(PgnError::UnbalancedVariation)?;
+            }
+            Token::Nag(nag) => tree.nodes[current].nags.push(nag),
+            Token::San(text) => {
+                let (san, suffix_nag) = split_suffix(&text);
+                let position = &tree.nodes[current].position;
+                let mv = san
+                    .parse::<san::Move>()
+                    .ok()
+                    .and_then(|parsed| parsed.into_move(position).ok())
+                    .ok_or_else(|| PgnError::IllegalMove(text.clone()))?;
+                let san = mv
+                    .san(position)
+                    .map(|san| san.to_string())
+                    .unwrap_or_else(|_| san.to_string());
+                current = tree.add_move(current, mv, san);
+                tree.nodes[current].comment_before = pending_comment.take();
+                tree.nodes[current].nags.extend(suffix_nag);
+            }
+            Token::Result => break,
+        }
+    }
+
+    if !variations.is_empty() {
+        return Err(PgnError::UnbalancedVariation);
+    }
+    if tree.nodes[GameTree::ROOT].children.is_empty() {
+        return Err(PgnError::NoMoves);
+    }
+    Ok(tree)
+}
+
+fn append(comment: &mut Option<String>, text: String) {
+    match comment {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(&text);
+        }
+        None => *comment = Some(text),
+    }
+}
+
+/// Splits move suffixes such as "!?" from the SAN, and returns their NAG.
+fn split_suffix(text: &str) -> (&str, Option<u8>) {
+    let san = text.trim_end_matches(['!', '?']);
+    let nag = match &text[san.len()..] {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    };
+    (san, nag)
+}
+
+fn tokenize(pgn: &str) -> Result<Vec<Token>, PgnError> {
+    let mut tokens = vec![];
+    let mut chars = pgn.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '[' => {
+                let content: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let (name, value) = content
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| PgnError::InvalidTag(content.clone()))?;
+                let value = value
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .ok_or_else(|| PgnError::InvalidTag(content.clone()))?;
+                let value = value.replace("\\\"", "\"").replace("\\\\", "\\");
+                tokens.push(Token::Tag(name.to_string(), value));
+            }
+            '{' => {
+                let mut comment = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    comment.push(c);
+                }
+                if !closed {
+                    return Err(PgnError::UnclosedComment);
+                }
+                tokens.push(Token::Comment(
+                    comment.split_whitespace().collect::<Vec<_>>().join(" "),
+                ));
+            }
+            ';' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '(' => tokens.push(Token::VariationStart),
+            ')' => tokens.push(Token::VariationEnd),
+            '$' => {
+                let mut digits = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(digit);
+                }
+                if let Ok(nag) = digits.parse() {
+                    tokens.push(Token::Nag(nag));
+                }
+            }
+            _ => {
+                let mut word = String::from(c);
+                while let Some(c) =
+                    chars.next_if(|&c| !c.is_whitespace() && !"{}()[];$".contains(c))
+                {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => tokens.push(Token::Result),
+                    _ => {
+                        // Move numbers may stick to the move, as in "12.Nf3" or "12...Kd7".
+                        let san = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                        if !san.is_empty() {
+                            tokens.push(Token::San(san.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::writer::PgnGame;
+
+    /// Nodes of the main line, the starting position excepted.
+    fn main_line(tree: &GameTree) -> Vec<&Node> {
+        let mut nodes = vec![];
+        let mut current = tree.node(GameTree::ROOT);
+        while let Some(&child) = current.children.first() {
+            current = tree.node(child);
+            nodes.push(current);
+        }
+        nodes
+    }
+
+    fn sans(tree: &GameTree) -> Vec<&str> {
+        main_line(tree)
+            .iter()
+            .map(|node| node.san.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn nested_variations() {
+        let tree = parse("1. e4 e5 (1... c5 2. Nf3 (2. c3) d6) 2. Nf3 Nc6 *").unwrap();
+        assert_eq!(sans(&tree), ["e4", "e5", "Nf3", "Nc6"]);
+
+        let e4 = tree.node(tree.node(GameTree::ROOT).children[0]);
+        assert_eq!(e4.children.len(), 2);
+        let c5 = tree.node(e4.children[1]);
+        assert_eq!(c5.san, "c5");
+        let nf3 = tree.node(c5.children[0]);
+        assert_eq!(nf3.san, "Nf3");
+        assert_eq!(tree.node(nf3.children[0]).san, "d6");
+        assert_eq!(tree.node(c5.children[1]).san, "c3");
+    }
+
+    #[test]
+    fn nags_and_move_suffixes() {
+        let tree = parse("1. e4! $14 e5?! 2. Qh5?? $4 *").unwrap();
+        let nags: Vec<_> = main_line(&tree)
+            .iter()
+            .map(|node| node.nags.clone())
+            .collect();
+        assert_eq!(nags, [vec![1, 14], vec![6], vec![4, 4]]);
+        assert_eq!(sans(&tree), ["e4", "e5", "Qh5"]);
+    }
+
+    #[test]
+    fn comments() {
+        let tree = parse(
+            "{Before the game} 1. e4 {Best by test}\n e5 ; King's pawn\n (1... c5 {The Sicilian}\n ({Or} 1... e6)) *",
+        )
+        .unwrap();
+        assert_eq!(
+            tree.node(GameTree::ROOT).comment.as_deref(),
+            Some("Before the game")
+        );
+        let line = main_line(&tree);
+        assert_eq!(line[0].comment.as_deref(), Some("Best by test"));
+        assert_eq!(line[1].comment.as_deref(), Some("King's pawn"));
+
+        let e4 = tree.node(tree.node(GameTree::ROOT).children[0]);
+        let c5 = tree.node(e4.children[1]);
+        assert_eq!(c5.comment.as_deref(), Some("The Sicilian"));
+        let e6 = tree.node(e4.children[2]);
+        assert_eq!(e6.comment_before.as_deref(), Some("Or"));
+    }
+
+    #[test]
+    fn setup_and_fen_tags() {
+        let fen = "8/8/8/4k3/8/8/4P3/4K3 w - - 0 1";
+        let tree = parse(&format!(
+            "[Event \"Lesson\"]\n[SetUp \"1\"]\n[FEN \"{}\"]\n\n1. Kd2 Kd5 2. Kd3 *",
+            fen
+        ))
+        .unwrap();
+        assert_eq!(tree.tag("Event"), Some("Lesson"));
+        assert_eq!(tree.node(GameTree::ROOT).position.as_fen(), fen);
+        assert_eq!(sans(&tree), ["Kd2", "Kd5", "Kd3"]);
+
+        assert!(matches!(
+            parse("[FEN \"not a position\"] 1. e4 *"),
+            Err(PgnError::InvalidFen(_))
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            parse("1. e4 {open"),
+            Err(PgnError::UnclosedComment)
+        ));
+        assert!(matches!(
+            parse("1. e4 (1. d4 *"),
+            Err(PgnError::UnbalancedVariation)
+        ));
+        assert!(matches!(
+            parse("(1. d4) 1. e4 *"),
+            Err(PgnError::VariationWithoutMove)
+        ));
+        assert!(matches!(parse("1. e5 *"), Err(PgnError::IllegalMove(_))));
+        assert!(matches!(parse("[Event \"?\"] *"), Err(PgnError::NoMoves)));
+    }
+
+    #[test]
+    fn writer_round_trip() {
+        let start = Board::from_fen("8/8/8/4k3/8/8/3QK3/8 b - - 3 40").unwrap();
+        let mut moves = vec![];
+        let mut position = start.clone();
+        for uci in ["e5e4", "d2d3", "e4e5", "e2f3", "e5f6", "d3d6"] {
+            let mv = Move::from_uci_legal(uci, &position).unwrap();
+            position = position.make_move(mv).unwrap();
+            moves.push(mv);
+        }
+        let pgn = PgnGame::new(&start, &moves, None)
+            .tag("White", "Player \"One\"")
+            .comment(0, "Black to move")
+            .comment(3, "The queen {cuts} the king off")
+            .write();
+
+        let tree = parse(&pgn).unwrap();
+        assert_eq!(tree.tag("White"), Some("Player \"One\""));
+        assert_eq!(tree.tag("Result"), Some("*"));
+        assert_eq!(tree.tag("SetUp"), Some("1"));
+        assert_eq!(tree.node(GameTree::ROOT).position.as_fen(), start.as_fen());
+        assert_eq!(
+            tree.node(GameTree::ROOT).comment.as_deref(),
+            Some("Black to move")
+        );
+
+        let line = main_line(&tree);
+        let parsed: Vec<_> = line.iter().map(|node| node.mv.unwrap()).collect();
+        assert_eq!(parsed, moves);
+        assert_eq!(line.last().unwrap().position.as_fen(), position.as_fen());
+        assert_eq!(
+            line[2].comment.as_deref(),
+            Some("The queen {cuts) the king off")
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use owlchess::{Board, Color, GameStatus, Move, Outcome};
+
+/// Longest line of movetext, as advised by the PGN standard.
+const MAX_LINE_LENGTH: usize = 79;
+
+/// A game ready to be exported in PGN, starting from any position.
+pub struct PgnGame {
+    tags: Vec<(String, String)>,
+    start: Board,
+    moves: Vec<Move>,
+    /// Comments by number of moves played before them, 0 being before the first move.
+    comments: Vec<(usize, String)>,
+    result: GameStatus,
+}
+
+impl PgnGame {
+    /// Creates a game with the seven standard tags, and the ones describing its starting position.
+    pub fn new(start: &Board, moves: &[Move], outcome: Option<Outcome>) -> Self {
+        let result = GameStatus::from(outcome);
+        let game = Self {
+            tags: vec![],
+            start: start.clone(),
+            moves: moves.to_vec(),
+            comments: vec![],
+            result,
+        };
+        game.tag("Event", "Endgame practice")
+            .tag("Site", "?")
+            .tag("Date", "????.??.??")
+            .tag("Round", "-")
+            .tag("White", "?")
+            .tag("Black", "?")
+            .tag("Result", &result.to_string())
+            .tag("SetUp", "1")
+            .tag("FEN", &start.as_fen())
+    }
+
+    /// Sets a tag, replacing its previous value if it was already set.
+    pub fn tag(mut self, name: &str, value: &str) -> Self {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+        self
+    }
+
+    /// Adds a comment after the first `ply` moves, next to the ones already there.
+    pub fn comment(mut self, ply: usize, text: &str) -> Self {
+        match self.comments.iter_mut().find(|(other_ply, _)| *other_ply == ply) {
+            Some((_, comment)) => {
+                comment.push(' ');
+                comment.push_str(text);
+            }
+            None => self.comments.push((ply, text.to_string())),
+        }
+        self
+    }
+
+    pub fn write(&self) -> String {
+        let mut pgn = String::new();
+        for (name, value) in &self.tags {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+        }
+        pgn.push('\n');
+
+        let mut tokens = vec![];
+        let mut position = self.start.clone();
+        for (index, mv) in self.moves.iter().enumerate() {
+            let commented = self.push_comment(&mut tokens, index);
+            let move_number = position.raw().move_number;
+            if position.side() == Color::White {
+                tokens.push(format!("{}.", move_number));
+            } else if index == 0 || commented {
+                tokens.push(format!("{}...", move_number));
+            }
+            match mv.san(&position) {
+                Ok(san) => tokens.push(san.to_string()),
+                Err(_) => break,
+            }
+            position = match position.make_move(*mv) {
+                Ok(next) => next,
+                Err(_) => break,
+            };
+        }
+        self.push_comment(&mut tokens, self.moves.len());
+        tokens.push(self.result.to_string());
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+                pgn.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+        pgn
+    }
+
+    /// Pushes the comment after the first `ply` moves, one token by word so that lines can
+    /// be wrapped inside it. Returns whether there was one.
+    fn push_comment(&self, tokens: &mut Vec<String>, ply: usize) -> bool {
+        let comment = match self.comments.iter().find(|(other_ply, _)| *other_ply == ply) {
+            Some((_, comment)) => comment.replace('}', ")"),
+            None => return false,
+        };
+        let words: Vec<&str> = comment.split_whitespace().collect();
+        let last = words.len().saturating_sub(1);
+        for (index, word) in words.iter().enumerate() {
+            let mut token = word.to_string();
+            if index == 0 {
+                token.insert(0, '{');
+            }
+            if index == last {
+                token.push('}');
+            }
+            tokens.push(token);
+        }
+        !words.is_empty()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,36 @@
+use owlchess::{Board, Move};
+
+pub mod native;
+pub mod syzygy;
+
+/// Game-theoretical value of a position, from the point of view of the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Wdl {
+    /// The same value, from the point of view of the other side.
+    pub fn inv(self) -> Self {
+        match self {
+            Self::Win => Self::Loss,
+            Self::Draw => Self::Draw,
+            Self::Loss => Self::Win,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Probe {
+    pub wdl: Wdl,
+    /// Best move for the side to move, if the tables allow to find one.
+    pub best_move: Option<Move>,
+}
+
+/// Any source of perfect endgame knowledge.
+pub trait Tablebase: Send {
+    /// Returns `None` if the position is not covered by the tables.
+    fn probe(&self, board: &Board) -> Option<Probe>;
+}
@@ -0,0 +1,359 @@
+use owlchess::{Board, CastlingRights, Cell, Color, Coord, File, Piece, Rank, RawBoard};
+
+use super::Material;
+
+/// White king squares kept for pawnless tables: a1-d1-d4 triangle, as (file, rank) from a1.
+const TRIANGLE: [(usize, usize); 10] = [
+    (0, 0),
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (1, 1),
+    (2, 1),
+    (3, 1),
+    (2, 2),
+    (3, 2),
+    (3, 3),
+];
+
+/// Maps the positions of one material to indices in a table and back.
+///
+/// Positions are reduced by symmetry: the white king is always brought to the
+/// a1-d1-d4 triangle in pawnless tables, and to the a-d files in tables with pawns.
+#[derive(Debug, Clone)]
+pub(super) struct Layout {
+    material: Material,
+    /// Pieces other than kings, in index order.
+    pieces: Vec<(Color, Piece)>,
+    has_pawns: bool,
+}
+
+impl Layout {
+    pub(super) fn new(material: &Material) -> Self {
+        let white = material.white.iter().map(|&piece| (Color::White, piece));
+        let black = material.black.iter().map(|&piece| (Color::Black, piece));
+        let pieces: Vec<_> = white.chain(black).collect();
+        let has_pawns = pieces.iter().any(|&(_, piece)| piece == Piece::Pawn);
+        Self {
+            material: material.clone(),
+            pieces,
+            has_pawns,
+        }
+    }
+
+    fn king_slots(&self) -> usize {
+        if self.has_pawns {
+            32
+        } else {
+            TRIANGLE.len()
+        }
+    }
+
+    fn per_side(&self) -> usize {
+        self.king_slots() * 64usize.pow(self.pieces.len() as u32 + 1)
+    }
+
+    pub(super) fn size(&self) -> usize {
+        2 * self.per_side()
+    }
+
+    /// Whether the board has exactly the material of this layout.
+    pub(super) fn matches(&self, board: &Board) -> bool {
+        Material::of(board) == self.material
+    }
+
+    /// Index of a board with the material of this layout.
+    ///
+    /// When several symmetries bring the white king to its reduced area (king on the a1-h8
+    /// diagonal), the smallest index is kept, so that symmetric positions share their index.
+    pub(super) fn index(&self, board: &Board) -> usize {
+        self.transforms_for(board.king_pos(Color::White))
+            .map(|transform| self.index_with(board, transform))
+            .min()
+            .expect("one of the symmetries must fit")
+    }
+
+    fn index_with(&self, board: &Board, transform: u8) -> usize {
+        let (file, rank) = apply(transform, board.king_pos(Color::White));
+        let king_slot = if self.has_pawns {
+            rank * 4 + file
+        } else {
+            TRIANGLE
+                .iter()
+                .position(|&square| square == (file, rank))
+                .expect("transform must bring the king in the triangle")
+        };
+
+        let side = match board.side() {
+            Color::White => 0,
+            Color::Black => 1,
+        };
+        let mut index = side * self.king_slots() + king_slot;
+        index = index * 64 + square_index(apply(transform, board.king_pos(Color::Black)));
+
+        // Identical pieces are sorted, so that swapping them gives the same index.
+        let mut start = 0;
+        while start < self.pieces.len() {
+            let (color, piece) = self.pieces[start];
+            let count = self.pieces[start..]
+                .iter()
+                .take_while(|&&other| other == (color, piece))
+                .count();
+            let mut squares: Vec<_> = board
+                .piece2(color, piece)
+                .into_iter()
+                .map(|coord| square_index(apply(transform, coord)))
+                .collect();
+            squares.sort_unstable();
+            for square in squares {
+                index = index * 64 + square;
+            }
+            start += count;
+        }
+        index
+    }
+
+    /// Board of the given index, if the index stands for a legal position.
+    pub(super) fn decode(&self, mut index: usize) -> Option<Board> {
+        let mut squares = vec![0; self.pieces.len() + 1];
+        for square in squares.iter_mut().rev() {
+            *square = index % 64;
+            index /= 64;
+        }
+        let king_slot = index % self.king_slots();
+        let side = if index / self.king_slots() == 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let white_king = if self.has_pawns {
+            (king_slot % 4, king_slot / 4)
+        } else {
+            TRIANGLE[king_slot]
+        };
+
+        let mut raw = RawBoard::empty();
+        raw.side = side;
+        raw.castling = CastlingRights::EMPTY;
+        raw.put(
+            from_square_index(white_king.1 * 8 + white_king.0),
+            Cell::from_parts(Color::White, Piece::King),
+        );
+        let others =
+            std::iter::once((Color::Black, Piece::King)).chain(self.pieces.iter().copied());
+        for ((color, piece), square) in others.zip(squares) {
+            let coord = from_square_index(square);
+            if raw.get(coord).is_occupied() {
+                return None;
+            }
+            raw.put(coord, Cell::from_parts(color, piece));
+        }
+        Board::try_from(raw).ok()
+    }
+
+    /// Indices of all the positions with the same material from which a move leads to `board`.
+    pub(super) fn predecessors(&self, board: &Board) -> Vec<usize> {
+        let mover = board.side().inv();
+        let mut raw = *board.raw();
+        raw.side = mover;
+        raw.ep_source = None;
+
+        let mut predecessors = vec![];
+        for destination in board.color(mover) {
+            let piece = match board.get(destination).piece() {
+                Some(piece) => piece,
+                None => continue,
+            };
+            for source in retro_sources(board, destination, piece, mover) {
+                let mut previous = raw;
+                previous.put(destination, Cell::EMPTY);
+                previous.put(source, Cell::from_parts(mover, piece));
+                if let Ok(previous) = Board::try_from(previous) {
+                    predecessors.push(self.index(&previous));
+                }
+            }
+        }
+        predecessors.sort_unstable();
+        predecessors.dedup();
+        predecessors
+    }
+
+    fn transforms_for(&self, white_king: Coord) -> impl Iterator<Item = u8> + '_ {
+        let count = if self.has_pawns { 2 } else { 8 };
+        (0..count).filter(move |&transform| {
+            let (file, rank) = apply(transform, white_king);
+            if self.has_pawns {
+                file <= 3
+            } else {
+                file <= 3 && rank <= file
+            }
+        })
+    }
+}
+
+/// Applies one of the 8 board symmetries, returning (file, rank) counted from a1.
+///
+/// Bit 2 swaps files and ranks, then bit 0 mirrors the files and bit 1 the ranks.
+fn apply(transform: u8, coord: Coord) -> (usize, usize) {
+    let mut file = coord.file().index();
+    let mut rank = 7 - coord.rank().index();
+    if transform & 4 != 0 {
+        std::mem::swap(&mut file, &mut rank);
+    }
+    if transform & 1 != 0 {
+        file = 7 - file;
+    }
+    if transform & 2 != 0 {
+        rank = 7 - rank;
+    }
+    (file, rank)
+}
+
+fn square_index((file, rank): (usize, usize)) -> usize {
+    rank * 8 + file
+}
+
+fn from_square_index(square: usize) -> Coord {
+    Coord::from_parts(
+        File::from_index(square % 8),
+        Rank::from_index(7 - square / 8),
+    )
+}
+
+/// Squares from which `piece` may have come to `destination` without capturing.
+fn retro_sources(board: &Board, destination: Coord, piece: Piece, color: Color) -> Vec<Coord> {
+    const KING: [(isize, isize); 8] = [
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+        (0, -1),
+        (0, 1),
+        (1, -1),
+        (1, 0),
+        (1, 1),
+    ];
+    const KNIGHT: [(isize, isize); 8] = [
+        (-2, -1),
+        (-2, 1),
+        (-1, -2),
+        (-1, 2),
+        (1, -2),
+        (1, 2),
+        (2, -1),
+        (2, 1),
+    ];
+    const DIAGONALS: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+    const LINES: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+    let empty = |coord: &Coord| board.get(*coord).is_free();
+    let steps = |deltas: &[(isize, isize)]| {
+        deltas
+            .iter()
+            .filter_map(|&(delta_file, delta_rank)| destination.shift(delta_file, delta_rank))
+            .filter(empty)
+            .collect::<Vec<_>>()
+    };
+    let rays = |deltas: &[(isize, isize)]| {
+        let mut sources = vec![];
+        for &(delta_file, delta_rank) in deltas {
+            let mut current = destination;
+            while let Some(next) = current.shift(delta_file, delta_rank) {
+                if !empty(&next) {
+                    break;
+                }
+                sources.push(next);
+                current = next;
+            }
+        }
+        sources
+    };
+
+    match piece {
+        Piece::King => steps(&KING),
+        Piece::Knight => steps(&KNIGHT),
+        Piece::Bishop => rays(&DIAGONALS),
+        Piece::Rook => rays(&LINES),
+        Piece::Queen => {
+            let mut sources = rays(&DIAGONALS);
+            sources.extend(rays(&LINES));
+            sources
+        }
+        Piece::Pawn => {
+            // Rank indices grow towards the 1st rank.
+            let (backward, double_push_rank) = match color {
+                Color::White => (1, Rank::R4),
+                Color::Black => (-1, Rank::R5),
+            };
+            let mut sources = vec![];
+            if let Some(single) = destination.shift(0, backward).filter(empty) {
+                sources.push(single);
+                if destination.rank() == double_push_rank {
+                    if let Some(double) = single.shift(0, backward).filter(empty) {
+                        sources.push(double);
+                    }
+                }
+            }
+            sources
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endgames::generator::MaterialSpec;
+
+    fn layout(material: &str) -> Layout {
+        Layout::new(&Material::from(&material.parse::<MaterialSpec>().unwrap()))
+    }
+
+    /// Board with every piece moved by `transform`.
+    fn transformed(board: &Board, transform: u8) -> Board {
+        let mut raw = *board.raw();
+        for file in 0..8 {
+            for rank in 0..8 {
+                let coord = Coord::from_parts(File::from_index(file), Rank::from_index(rank));
+                raw.put(
+                    from_square_index(square_index(apply(transform, coord))),
+                    board.get(coord),
+                );
+            }
+        }
+        Board::try_from(raw).unwrap()
+    }
+
+    #[test]
+    fn symmetric_positions_share_their_index() {
+        let layout = layout("KBNvK");
+        let board = Board::from_fen("8/8/2k5/8/5N2/1B6/6K1/8 b - - 0 1").unwrap();
+        let index = layout.index(&board);
+        for transform in 0..8 {
+            assert_eq!(layout.index(&transformed(&board, transform)), index);
+        }
+    }
+
+    #[test]
+    fn pawn_positions_share_their_index_with_the_file_mirror() {
+        let layout = layout("KPvK");
+        let board = Board::from_fen("8/8/2k5/8/5P2/8/6K1/8 w - - 0 1").unwrap();
+        assert_eq!(layout.index(&transformed(&board, 1)), layout.index(&board));
+    }
+
+    #[test]
+    fn index_round_trip() {
+        for material in ["KQvK", "KPvK", "KRvKN"] {
+            let layout = layout(material);
+            let mut legal = 0;
+            for index in (0..layout.size()).step_by(97) {
+                if let Some(board) = layout.decode(index) {
+                    let canonical = layout.index(&board);
+                    assert!(canonical <= index, "{material}: {index} gives {canonical}");
+                    let decoded = layout.decode(canonical).expect("canonical index is legal");
+                    assert_eq!(layout.index(&decoded), canonical, "{material}");
+                    legal += 1;
+                }
+            }
+            assert!(legal > 0, "{material}");
+        }
+    }
+}
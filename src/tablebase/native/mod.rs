@@ -0,0 +1,551 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use owlchess::{
+    movegen::legal, Board, CastlingRights, Cell, Color, Coord, DrawReason, Move, Piece, RawBoard,
+};
+
+use super::{Probe, Tablebase, Wdl};
+use crate::endgames::generator::MaterialSpec;
+use layout::Layout;
+
+mod layout;
+mod retrograde;
+
+/// Largest number of pieces, kings included, for which tables may be generated.
+pub const MAX_PIECES: usize = 4;
+
+const FILE_MAGIC: &[u8; 4] = b"BCET";
+const FILE_VERSION: u8 = 1;
+const FILE_EXTENSION: &str = "bcet";
+
+/// Stored value of a drawn position.
+const DRAW: u8 = 0;
+/// Stored value of an index which does not stand for a legal position.
+const INVALID: u8 = u8::MAX;
+
+/// Distance to mate in plies, from the point of view of the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dtm {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+impl Dtm {
+    /// Number of moves of the winning side until mate, if the game is not drawn.
+    pub fn moves_to_mate(&self) -> Option<u8> {
+        match self {
+            Self::Win(plies) => Some(plies.div_ceil(2)),
+            Self::Loss(plies) => Some(plies / 2),
+            Self::Draw => None,
+        }
+    }
+
+    pub fn wdl(&self) -> Wdl {
+        match self {
+            Self::Win(_) => Wdl::Win,
+            Self::Loss(_) => Wdl::Loss,
+            Self::Draw => Wdl::Draw,
+        }
+    }
+}
+
+fn encode(dtm: Dtm) -> u8 {
+    match dtm {
+        Dtm::Win(plies) | Dtm::Loss(plies) => {
+            assert!(
+                plies < INVALID - 1,
+                "distance to mate too long to be stored"
+            );
+            plies + 1
+        }
+        Dtm::Draw => DRAW,
+    }
+}
+
+fn decode(value: u8) -> Option<Dtm> {
+    match value {
+        DRAW => Some(Dtm::Draw),
+        INVALID => None,
+        value if (value - 1) % 2 == 0 => Some(Dtm::Loss(value - 1)),
+        value => Some(Dtm::Win(value - 1)),
+    }
+}
+
+#[derive(Debug)]
+pub enum NativeTablebaseError {
+    Io(io::Error),
+    InvalidFile(PathBuf),
+    TooManyPieces(String),
+}
+
+impl fmt::Display for NativeTablebaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::InvalidFile(path) => write!(f, "invalid table file : {}", path.display()),
+            Self::TooManyPieces(material) => write!(
+                f,
+                "{} has more than {} pieces, its table would be too large",
+                material, MAX_PIECES
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NativeTablebaseError {}
+
+impl From<io::Error> for NativeTablebaseError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Pieces of both sides, kings excepted, strongest first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Material {
+    white: Vec<Piece>,
+    black: Vec<Piece>,
+}
+
+impl Material {
+    fn new(mut white: Vec<Piece>, mut black: Vec<Piece>) -> Self {
+        white.retain(|&piece| piece != Piece::King);
+        black.retain(|&piece| piece != Piece::King);
+        white.sort_by_key(|&piece| order(piece));
+        black.sort_by_key(|&piece| order(piece));
+        Self { white, black }
+    }
+
+    fn of(board: &Board) -> Self {
+        let pieces = |color| {
+            [
+                Piece::Queen,
+                Piece::Rook,
+                Piece::Bishop,
+                Piece::Knight,
+                Piece::Pawn,
+            ]
+            .into_iter()
+            .flat_map(|piece| vec![piece; board.piece2(color, piece).len() as usize])
+            .collect()
+        };
+        Self::new(pieces(Color::White), pieces(Color::Black))
+    }
+
+    fn piece_count(&self) -> usize {
+        2 + self.white.len() + self.black.len()
+    }
+
+    /// Orientation under which the table is stored: the stronger side plays White.
+    ///
+    /// Returns whether the colors had to be swapped.
+    fn canonical(self) -> (Self, bool) {
+        let strength = |pieces: &[Piece]| {
+            (
+                pieces.iter().map(|&p| value(p)).sum::<u32>(),
+                side_name(pieces),
+            )
+        };
+        if strength(&self.black) > strength(&self.white) {
+            (
+                Self {
+                    white: self.black,
+                    black: self.white,
+                },
+                true,
+            )
+        } else {
+            (self, false)
+        }
+    }
+
+    /// Materials reached by one capture or one promotion.
+    fn successors(&self) -> Vec<Material> {
+        let mut successors = vec![];
+        for (own, other, white) in [
+            (&self.white, &self.black, true),
+            (&self.black, &self.white, false),
+        ] {
+            for (position, &piece) in own.iter().enumerate() {
+                let mut changed = own.clone();
+                changed.remove(position);
+                let mut variants = vec![changed.clone()];
+                if piece == Piece::Pawn {
+                    for promoted in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+                        let mut promotion = changed.clone();
+                        promotion.push(promoted);
+                        variants.push(promotion);
+                    }
+                }
+                for variant in variants {
+                    successors.push(if white {
+                        Self::new(variant, other.clone())
+                    } else {
+                        Self::new(other.clone(), variant)
+                    });
+                }
+            }
+        }
+        successors
+    }
+
+    /// Whether mate is impossible, so that every position is a draw.
+    fn is_insufficient(&self) -> bool {
+        let minor = |pieces: &[Piece]| match pieces {
+            [] => true,
+            [piece] => *piece == Piece::Bishop || *piece == Piece::Knight,
+            _ => false,
+        };
+        (self.white.is_empty() && minor(&self.black))
+            || (self.black.is_empty() && minor(&self.white))
+    }
+
+    fn name(&self) -> String {
+        format!("{}v{}", side_name(&self.white), side_name(&self.black))
+    }
+}
+
+impl From<&MaterialSpec> for Material {
+    fn from(spec: &MaterialSpec) -> Self {
+        Self::new(spec.white.clone(), spec.black.clone())
+    }
+}
+
+fn order(piece: Piece) -> u8 {
+    match piece {
+        Piece::King => 0,
+        Piece::Queen => 1,
+        Piece::Rook => 2,
+        Piece::Bishop => 3,
+        Piece::Knight => 4,
+        Piece::Pawn => 5,
+    }
+}
+
+fn value(piece: Piece) -> u32 {
+    match piece {
+        Piece::Queen => 9,
+        Piece::Rook => 5,
+        Piece::Bishop | Piece::Knight => 3,
+        Piece::Pawn => 1,
+        Piece::King => 0,
+    }
+}
+
+fn side_name(pieces: &[Piece]) -> String {
+    let mut name = String::from("K");
+    name.extend(
+        pieces
+            .iter()
+            .map(|&piece| Cell::from_parts(Color::White, piece).as_char()),
+    );
+    name
+}
+
+/// The same position with the colors swapped and the board mirrored vertically.
+fn swap_colors(board: &Board) -> Board {
+    let mut raw = RawBoard::empty();
+    for coord in Coord::iter() {
+        let cell = board.get(coord);
+        if let (Some(color), Some(piece)) = (cell.color(), cell.piece()) {
+            raw.put(coord.flipped_rank(), Cell::from_parts(color.inv(), piece));
+        }
+    }
+    raw.side = board.side().inv();
+    Board::try_from(raw).expect("mirrored position stays valid")
+}
+
+struct Table {
+    layout: Layout,
+    values: Vec<u8>,
+}
+
+impl Table {
+    fn dtm(&self, board: &Board) -> Option<Dtm> {
+        decode(self.values[self.layout.index(board)])
+    }
+}
+
+/// Distance to mate tables generated by retrograde analysis, for endgames of up to
+/// [`MAX_PIECES`] pieces.
+///
+/// Tables may be generated in memory, saved to a directory and loaded back from it.
+/// Positions with castling rights are not covered, and en passant is ignored.
+#[derive(Default)]
+pub struct NativeTablebase {
+    tables: HashMap<Material, Table>,
+}
+
+impl NativeTablebase {
+    /// Generates the table of `material`, and first the ones of the endgames it may turn into.
+    ///
+    /// Returns the names of the newly generated tables.
+    pub fn generate(
+        &mut self,
+        material: &MaterialSpec,
+    ) -> Result<Vec<String>, NativeTablebaseError> {
+        let (material, _) = Material::from(material).canonical();
+        if material.piece_count() > MAX_PIECES {
+            return Err(NativeTablebaseError::TooManyPieces(material.name()));
+        }
+        let mut generated = vec![];
+        self.generate_with_successors(material, &mut generated);
+        Ok(generated)
+    }
+
+    fn generate_with_successors(&mut self, material: Material, generated: &mut Vec<String>) {
+        if material.is_insufficient() || self.tables.contains_key(&material) {
+            return;
+        }
+        for successor in material.successors() {
+            self.generate_with_successors(successor.canonical().0, generated);
+        }
+
+        let layout = Layout::new(&material);
+        let values = retrograde::generate(&layout, |board| self.probe_dtm(board));
+        generated.push(material.name());
+        self.tables.insert(material, Table { layout, values });
+    }
+
+    /// Loads every table file found in `dir`.
+    pub fn load_directory(dir: impl AsRef<Path>) -> Result<Self, NativeTablebaseError> {
+        let mut tablebase = Self::default();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(FILE_EXTENSION) {
+                continue;
+            }
+            let (material, values) = read_table(&path)?;
+            let layout = Layout::new(&material);
+            if values.len() != layout.size() {
+                return Err(NativeTablebaseError::InvalidFile(path));
+            }
+            tablebase.tables.insert(material, Table { layout, values });
+        }
+        Ok(tablebase)
+    }
+
+    /// Writes every table to `dir`, one file per material.
+    pub fn save_directory(&self, dir: impl AsRef<Path>) -> Result<(), NativeTablebaseError> {
+        fs::create_dir_all(&dir)?;
+        for (material, table) in &self.tables {
+            let path = dir
+                .as_ref()
+                .join(format!("{}.{}", material.name(), FILE_EXTENSION));
+            write_table(&path, material, &table.values)?;
+        }
+        Ok(())
+    }
+
+    /// Returns `None` if the position is not covered by the loaded tables.
+    pub fn probe_dtm(&self, board: &Board) -> Option<Dtm> {
+        if board.raw().castling != CastlingRights::EMPTY {
+            return None;
+        }
+        if board.calc_draw_simple() == Some(DrawReason::InsufficientMaterial) {
+            return Some(Dtm::Draw);
+        }
+        let (material, swapped) = Material::of(board).canonical();
+        let table = self.tables.get(&material)?;
+        if swapped {
+            table.dtm(&swap_colors(board))
+        } else {
+            table.dtm(board)
+        }
+    }
+
+    /// Fastest mate when winning, longest resistance when losing, any drawing move otherwise.
+    pub fn best_move(&self, board: &Board) -> Option<Move> {
+        let mut best = None;
+        for mv in legal::gen_all(board).iter() {
+            let child = board.make_move(*mv).expect("legal move");
+            let score = match self.probe_dtm(&child)? {
+                Dtm::Loss(plies) => plies as i32 - 1000,
+                Dtm::Draw => 0,
+                Dtm::Win(plies) => 1000 - plies as i32,
+            };
+            if best.is_none_or(|(best_score, _)| score < best_score) {
+                best = Some((score, *mv));
+            }
+        }
+        best.map(|(_, mv)| mv)
+    }
+}
+
+impl Tablebase for NativeTablebase {
+    fn probe(&self, board: &Board) -> Option<Probe> {
+        let dtm = self.probe_dtm(board)?;
+        Some(Probe {
+            wdl: dtm.wdl(),
+            best_move: self.best_move(board),
+        })
+    }
+}
+
+/// Table files hold a small header followed by the values, run-length encoded as
+/// (value, run length) byte pairs.
+fn write_table(path: &Path, material: &Material, values: &[u8]) -> io::Result<()> {
+    let name = material.name();
+    let mut bytes = Vec::with_capacity(values.len() / 8);
+    bytes.extend_from_slice(FILE_MAGIC);
+    bytes.push(FILE_VERSION);
+    bytes.push(name.len() as u8);
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.extend_from_slice(&(values.len() as u32).to_le_bytes());
+
+    let mut position = 0;
+    while position < values.len() {
+        let value = values[position];
+        let run = values[position..]
+            .iter()
+            .take(u8::MAX as usize)
+            .take_while(|&&other| other == value)
+            .count();
+        bytes.push(value);
+        bytes.push(run as u8);
+        position += run;
+    }
+    fs::File::create(path)?.write_all(&bytes)
+}
+
+fn read_table(path: &Path) -> Result<(Material, Vec<u8>), NativeTablebaseError> {
+    let invalid = || NativeTablebaseError::InvalidFile(path.to_path_buf());
+    let mut bytes = vec![];
+    fs::File::open(path)?.read_to_end(&mut bytes)?;
+
+    let header_length = FILE_MAGIC.len() + 2;
+    if bytes.len() < header_length || &bytes[..4] != FILE_MAGIC || bytes[4] != FILE_VERSION {
+        return Err(invalid());
+    }
+    let name_end = header_length + bytes[5] as usize;
+    let name = bytes
+        .get(header_length..name_end)
+        .and_then(|name| std::str::from_utf8(name).ok())
+        .ok_or_else(invalid)?;
+    let spec: MaterialSpec = name.parse().map_err(|_| invalid())?;
+    let material = Material::from(&spec);
+    let count = bytes
+        .get(name_end..name_end + 4)
+        .map(|count| u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize)
+        .ok_or_else(invalid)?;
+
+    let mut values = Vec::with_capacity(count);
+    for pair in bytes[name_end + 4..].chunks(2) {
+        match pair {
+            [value, run] => values.extend(std::iter::repeat_n(*value, *run as usize)),
+            _ => return Err(invalid()),
+        }
+    }
+    if values.len() != count {
+        return Err(invalid());
+    }
+    Ok((material, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated(material: &str) -> NativeTablebase {
+        let mut tablebase = NativeTablebase::default();
+        tablebase.generate(&material.parse().unwrap()).unwrap();
+        tablebase
+    }
+
+    /// Longest win stored in the table of `material`, in plies.
+    fn longest_win(tablebase: &NativeTablebase, material: &str) -> u8 {
+        let spec: MaterialSpec = material.parse().unwrap();
+        let (material, _) = Material::from(&spec).canonical();
+        tablebase.tables[&material]
+            .values
+            .iter()
+            .filter_map(|&value| match decode(value) {
+                Some(Dtm::Win(plies)) => Some(plies),
+                _ => None,
+            })
+            .max()
+            .unwrap()
+    }
+
+    fn probe(tablebase: &NativeTablebase, fen: &str) -> Option<Dtm> {
+        tablebase.probe_dtm(&Board::from_fen(fen).unwrap())
+    }
+
+    fn assert_longest_mate(material: &str, moves: u8) {
+        let tablebase = generated(material);
+        let plies = longest_win(&tablebase, material);
+        assert_eq!(plies.div_ceil(2), moves, "{}", material);
+    }
+
+    #[test]
+    fn longest_mates() {
+        assert_longest_mate("KQvK", 10);
+        assert_longest_mate("KRvK", 16);
+    }
+
+    #[test]
+    #[ignore = "generates large tables, run with --release --ignored"]
+    fn longest_mate_with_a_pawn() {
+        assert_longest_mate("KPvK", 28);
+    }
+
+    #[test]
+    #[ignore = "generates large tables, run with --release --ignored"]
+    fn longest_mate_with_bishop_and_knight() {
+        assert_longest_mate("KBNvK", 33);
+    }
+
+    #[test]
+    fn probes_from_both_sides() {
+        let tablebase = generated("KQvK");
+        assert_eq!(
+            probe(&tablebase, "7k/6Q1/6K1/8/8/8/8/8 b - - 0 1"),
+            Some(Dtm::Loss(0))
+        );
+        assert_eq!(
+            probe(&tablebase, "7k/8/6K1/8/8/8/8/1Q6 w - - 0 1"),
+            Some(Dtm::Win(1))
+        );
+        assert_eq!(
+            probe(&tablebase, "8/8/8/8/8/6k1/6q1/7K w - - 0 1"),
+            Some(Dtm::Loss(0))
+        );
+        assert_eq!(
+            probe(&tablebase, "7k/8/6K1/8/8/8/8/8 w - - 0 1"),
+            Some(Dtm::Draw)
+        );
+        assert_eq!(probe(&tablebase, "7k/8/6K1/8/8/8/8/6R1 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn table_file_round_trip() {
+        let material = Material::from(&"KRvK".parse::<MaterialSpec>().unwrap());
+        // Runs longer than 255 values are split over several pairs.
+        let mut values = vec![DRAW; 1000];
+        values.extend([INVALID, 3, 3, 7]);
+        values.extend(vec![12; 256]);
+        let path = std::env::temp_dir().join(format!(
+            "basic-chess-endgames-{}.{}",
+            std::process::id(),
+            FILE_EXTENSION
+        ));
+
+        write_table(&path, &material, &values).unwrap();
+        let (read_material, read_values) = read_table(&path).unwrap();
+        assert_eq!(read_material, material);
+        assert_eq!(read_values, values);
+
+        // A truncated file is rejected.
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        let result = read_table(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(NativeTablebaseError::InvalidFile(_))));
+    }
+}
@@ -0,0 +1,117 @@
+use owlchess::{movegen::legal, Board};
+
+use super::{encode, layout::Layout, Dtm, INVALID};
+
+/// Computes the distance to mate of every position of `layout`.
+///
+/// Moves leaving the table (captures and promotions) are valued with `exits`, which must
+/// know every smaller table; positions it does not know are considered drawn.
+pub(super) fn generate(layout: &Layout, exits: impl Fn(&Board) -> Option<Dtm>) -> Vec<u8> {
+    let size = layout.size();
+    let mut values = vec![encode(Dtm::Draw); size];
+    let mut resolved = vec![false; size];
+    // In-table successors not yet known to win for the opponent.
+    let mut remaining = vec![0u8; size];
+    // Longest resistance found so far, should the position turn out to be lost.
+    let mut loss_plies = vec![0u8; size];
+    // Cleared as soon as one move is known not to lose.
+    let mut losable = vec![true; size];
+    let mut buckets: Vec<Vec<u32>> = vec![];
+
+    // Forward pass: terminal positions, moves leaving the table and successor counts.
+    for index in 0..size {
+        let board = match layout.decode(index) {
+            Some(board) if layout.index(&board) == index => board,
+            _ => {
+                values[index] = INVALID;
+                resolved[index] = true;
+                continue;
+            }
+        };
+
+        let moves = legal::gen_all(&board);
+        if moves.is_empty() {
+            if board.is_check() {
+                schedule(&mut buckets, 0, index);
+            } else {
+                resolved[index] = true;
+            }
+            continue;
+        }
+
+        let mut successors = vec![];
+        let mut best_win = None;
+        for mv in moves.iter() {
+            let child = board.make_move(*mv).expect("legal move");
+            if layout.matches(&child) {
+                successors.push(layout.index(&child));
+                continue;
+            }
+            match exits(&child) {
+                Some(Dtm::Loss(plies)) => {
+                    best_win = Some(best_win.map_or(plies + 1, |best: u8| best.min(plies + 1)));
+                }
+                Some(Dtm::Win(plies)) => loss_plies[index] = loss_plies[index].max(plies + 1),
+                Some(Dtm::Draw) | None => losable[index] = false,
+            }
+        }
+        successors.sort_unstable();
+        successors.dedup();
+        remaining[index] = successors.len() as u8;
+
+        if let Some(plies) = best_win {
+            losable[index] = false;
+            schedule(&mut buckets, plies as usize, index);
+        } else if successors.is_empty() {
+            if losable[index] {
+                schedule(&mut buckets, loss_plies[index] as usize, index);
+            } else {
+                resolved[index] = true;
+            }
+        }
+    }
+
+    // Retrograde pass: positions are resolved by increasing distance to mate, so the first
+    // value given to a position is always the best one for the side to move.
+    let mut plies = 0;
+    while plies < buckets.len() {
+        let bucket = std::mem::take(&mut buckets[plies]);
+        for index in bucket {
+            let index = index as usize;
+            if resolved[index] {
+                continue;
+            }
+            resolved[index] = true;
+            values[index] = encode(if plies % 2 == 0 {
+                Dtm::Loss(plies as u8)
+            } else {
+                Dtm::Win(plies as u8)
+            });
+
+            let board = layout.decode(index).expect("resolved positions are valid");
+            for predecessor in layout.predecessors(&board) {
+                if resolved[predecessor] {
+                    continue;
+                }
+                if plies % 2 == 0 {
+                    schedule(&mut buckets, plies + 1, predecessor);
+                } else {
+                    remaining[predecessor] = remaining[predecessor].saturating_sub(1);
+                    loss_plies[predecessor] = loss_plies[predecessor].max(plies as u8 + 1);
+                    if remaining[predecessor] == 0 && losable[predecessor] {
+                        schedule(&mut buckets, loss_plies[predecessor] as usize, predecessor);
+                    }
+                }
+            }
+        }
+        plies += 1;
+    }
+    values
+}
+
+fn schedule(buckets: &mut Vec<Vec<u32>>, plies: usize, index: usize) {
+    if buckets.len() <= plies {
+        buckets.resize_with(plies + 1, Vec::new);
+    }
+    buckets[plies].push(index as u32);
+}
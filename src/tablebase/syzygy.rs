@@ -0,0 +1,58 @@
+use std::{io, path::Path};
+
+use owlchess::{moves::uci, Board, Move};
+use shakmaty::{fen::Fen, uci::UciMove, CastlingMode, Chess};
+use shakmaty_syzygy::{Tablebase as Tables, Wdl as SyzygyWdl};
+
+use super::{Probe, Tablebase, Wdl};
+
+/// Syzygy WDL/DTZ tables read from a local directory.
+pub struct SyzygyTablebase {
+    tables: Tables<Chess>,
+}
+
+impl SyzygyTablebase {
+    /// Opens all the tables of the directory. Fails if there is none.
+    pub fn open(directory: impl AsRef<Path>) -> io::Result<Self> {
+        let mut tables = Tables::new();
+        if tables.add_directory(directory)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no syzygy table in directory",
+            ));
+        }
+        Ok(Self { tables })
+    }
+}
+
+impl Tablebase for SyzygyTablebase {
+    fn probe(&self, board: &Board) -> Option<Probe> {
+        let pieces = board.color(owlchess::Color::White) | board.color(owlchess::Color::Black);
+        if pieces.len() as usize > self.tables.max_pieces() {
+            return None;
+        }
+
+        let position = to_shakmaty(board)?;
+        // Wins and losses which cannot be completed within the 50 moves rule are draws.
+        let wdl = match self.tables.probe_wdl_after_zeroing(&position).ok()? {
+            SyzygyWdl::Win => Wdl::Win,
+            SyzygyWdl::Loss => Wdl::Loss,
+            SyzygyWdl::CursedWin | SyzygyWdl::Draw | SyzygyWdl::BlessedLoss => Wdl::Draw,
+        };
+        let best_move = match self.tables.best_move(&position) {
+            Ok(Some((best_move, _))) => from_shakmaty(&best_move, board),
+            _ => None,
+        };
+        Some(Probe { wdl, best_move })
+    }
+}
+
+fn to_shakmaty(board: &Board) -> Option<Chess> {
+    let fen: Fen = board.as_fen().parse().ok()?;
+    fen.into_position(CastlingMode::Standard).ok()
+}
+
+fn from_shakmaty(best_move: &shakmaty::Move, board: &Board) -> Option<Move> {
+    let uci_move: uci::Move = UciMove::from_standard(best_move).to_string().parse().ok()?;
+    uci_move.into_move(board).ok()
+}